@@ -0,0 +1,111 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Allocations and time per record for a few configurations, run with
+//! `cargo bench`. Formatting builds the timestamp and the prefix fields
+//! straight into the record buffer; building them as strings first shows up
+//! here as more allocations per record.
+
+use log::{Level, Record};
+use sdre_rust_logging::env_logger::Target;
+use sdre_rust_logging::{ColorPreference, LogFormat, LoggingConfig};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// Counts the allocations made on each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The number of allocations `f` makes on this thread
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// A writer that throws the output away
+struct Discard;
+
+impl Write for Discard {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Log `RECORDS` records through a logger built from `config` and print the
+/// allocations and the time per record
+fn bench(name: &str, config: LoggingConfig) {
+    const RECORDS: u32 = 100_000;
+    let logger = config
+        .set_builder_hook(|builder| {
+            builder.target(Target::Pipe(Box::new(Discard)));
+        })
+        .build();
+    let log = || {
+        logger.log(
+            &Record::builder()
+                .args(format_args!("tuned"))
+                .level(Level::Info)
+                .target("app")
+                .build(),
+        );
+    };
+    // the first record loads the local timezone and sizes the buffers
+    log();
+    let count = allocations(log);
+    let start = Instant::now();
+    for _ in 0..RECORDS {
+        log();
+    }
+    let per_record = start.elapsed() / RECORDS;
+    println!("{name:<12} {count:>3} allocations {per_record:>10?} per record");
+}
+
+fn main() {
+    bench(
+        "plain",
+        LoggingConfig::new().set_color(ColorPreference::Never),
+    );
+    bench(
+        "colored",
+        LoggingConfig::new().set_color(ColorPreference::Always),
+    );
+    bench("compact", LoggingConfig::compact());
+    bench("json", LoggingConfig::new().set_format(LogFormat::Json));
+}
@@ -0,0 +1,56 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Print a file written with `LogFormat::Binary` as plain text
+//!
+//! `cargo run --example decode_binary -- trace.bin`
+
+use sdre_rust_logging::BinaryReader;
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: decode_binary <file>");
+        return ExitCode::FAILURE;
+    };
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!("Could not open {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for record in BinaryReader::new(BufReader::new(file)) {
+        match record {
+            Ok(record) => println!(
+                "[{: <5}][{}]{}",
+                record.level,
+                record.timestamp.format("%Y-%m-%dT%H:%M:%S%.6fZ"),
+                record.message
+            ),
+            Err(error) => {
+                eprintln!("Could not decode {path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,104 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Logger writing to the Android log (logcat)
+
+use crate::format::{RecordFormatter, FATAL_TARGET, FLUSH_TARGET};
+use crate::{stats, LoggingConfig};
+use log::{Level, Log, Metadata, Record};
+use std::ffi::{c_char, c_int, CString};
+
+const ANDROID_LOG_VERBOSE: c_int = 2;
+const ANDROID_LOG_DEBUG: c_int = 3;
+const ANDROID_LOG_INFO: c_int = 4;
+const ANDROID_LOG_WARN: c_int = 5;
+const ANDROID_LOG_ERROR: c_int = 6;
+const ANDROID_LOG_FATAL: c_int = 7;
+
+#[link(name = "log")]
+extern "C" {
+    fn __android_log_write(prio: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+}
+
+/// The logcat priority for `record`
+fn priority(record: &Record) -> c_int {
+    if record.target() == FATAL_TARGET {
+        return ANDROID_LOG_FATAL;
+    }
+
+    match record.level() {
+        Level::Error => ANDROID_LOG_ERROR,
+        Level::Warn => ANDROID_LOG_WARN,
+        Level::Info => ANDROID_LOG_INFO,
+        Level::Debug => ANDROID_LOG_DEBUG,
+        Level::Trace => ANDROID_LOG_VERBOSE,
+    }
+}
+
+/// Make `value` safe to pass to C by escaping interior NUL bytes
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "\\x00")).unwrap_or_default()
+}
+
+pub(crate) struct AndroidLogger {
+    config: LoggingConfig,
+    tag: CString,
+    formatter: RecordFormatter,
+}
+
+impl AndroidLogger {
+    pub(crate) fn new(tag: &str, config: LoggingConfig) -> Self {
+        Self {
+            tag: c_string(tag),
+            formatter: RecordFormatter::new(config.clone()),
+            config,
+        }
+    }
+}
+
+impl Log for AndroidLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        crate::force::enabled(&self.config, metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata())
+            || record.target() == FLUSH_TARGET
+            || !self.formatter.keep(record)
+        {
+            return;
+        }
+        let Some(suppressed) = self.formatter.cooldown(record) else {
+            return;
+        };
+        stats::count_record(record.level());
+
+        // logcat records its own time, level and tag, so only the message is sent
+        let mut message = self.formatter.render_message(record);
+        if suppressed > 0 {
+            message.push_str(&format!(" ({suppressed} suppressed)"));
+        }
+        let message = c_string(&message);
+        // SAFETY: both pointers come from `CString`s, so they are valid and
+        // NUL terminated, and `self.tag` and `message` outlive the call, which
+        // does not keep them
+        unsafe {
+            __android_log_write(priority(record), self.tag.as_ptr(), message.as_ptr());
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,199 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Compact binary encoding for `LogFormat::Binary`<br><br>
+//! Every record is one frame, all integers are little endian:
+//! - `u32` length of the rest of the frame
+//! - `u8` frame version, currently 1
+//! - `i64` timestamp in microseconds since the Unix epoch, UTC
+//! - `u8` level, using the integers of `level_from_int`
+//! - `u16` length of the target, followed by the target as UTF-8
+//! - the message as UTF-8, filling the rest of the frame
+//!
+//! Files written in this format are read back with `BinaryReader`.
+
+use chrono::{DateTime, Utc};
+use log::{Level, Record};
+use std::io::{self, Read, Write};
+
+const FRAME_VERSION: u8 = 1;
+/// The fixed size part of a frame after the length: version, timestamp, level and target length
+const FRAME_HEADER_LEN: usize = 1 + 8 + 1 + 2;
+
+fn level_to_byte(level: Level) -> u8 {
+    match level {
+        Level::Error => 1,
+        Level::Warn => 2,
+        Level::Info => 3,
+        Level::Debug => 4,
+        Level::Trace => 5,
+    }
+}
+
+fn level_from_byte(level: u8) -> Option<Level> {
+    match level {
+        1 => Some(Level::Error),
+        2 => Some(Level::Warn),
+        3 => Some(Level::Info),
+        4 => Some(Level::Debug),
+        5 => Some(Level::Trace),
+        _ => None,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Encode `record` with `message` as its rendered message and write it as one frame
+pub(crate) fn write_frame<W: Write + ?Sized>(
+    buf: &mut W,
+    record: &Record,
+    timestamp: DateTime<Utc>,
+    message: &str,
+) -> io::Result<()> {
+    let target = record.target().as_bytes();
+    let target = &target[..target.len().min(usize::from(u16::MAX))];
+    let frame_len = FRAME_HEADER_LEN + target.len() + message.len();
+    let frame_len =
+        u32::try_from(frame_len).map_err(|_| invalid_data("record too large for a frame"))?;
+
+    let mut frame = Vec::with_capacity(4 + frame_len as usize);
+    frame.extend_from_slice(&frame_len.to_le_bytes());
+    frame.push(FRAME_VERSION);
+    frame.extend_from_slice(&timestamp.timestamp_micros().to_le_bytes());
+    frame.push(level_to_byte(record.level()));
+    frame.extend_from_slice(&(target.len() as u16).to_le_bytes());
+    frame.extend_from_slice(target);
+    frame.extend_from_slice(message.as_bytes());
+    buf.write_all(&frame)
+}
+
+/// A record decoded from `LogFormat::Binary` output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryRecord {
+    pub timestamp: DateTime<Utc>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Iterator over the records of `LogFormat::Binary` output<br><br>
+/// `for record in BinaryReader::new(File::open("trace.bin")?) { println!("{:?}", record?); }`<br><br>
+/// Iteration stops at the end of the input. A frame cut short by the end of the
+/// input, such as the last frame of a file that is still being written, is
+/// returned as an `UnexpectedEof` error.
+pub struct BinaryReader<R> {
+    reader: R,
+}
+
+impl<R: Read> BinaryReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<BinaryRecord>> {
+        let mut len = [0u8; 4];
+        match self.reader.read(&mut len[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut len[1..])?,
+        }
+
+        let frame_len = u32::from_le_bytes(len) as usize;
+        if frame_len < FRAME_HEADER_LEN {
+            return Err(invalid_data("frame too short"));
+        }
+
+        let mut frame = vec![0u8; frame_len];
+        self.reader.read_exact(&mut frame)?;
+
+        if frame[0] != FRAME_VERSION {
+            return Err(invalid_data("unsupported frame version"));
+        }
+
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&frame[1..9]);
+        let timestamp = DateTime::from_timestamp_micros(i64::from_le_bytes(timestamp))
+            .ok_or_else(|| invalid_data("timestamp out of range"))?;
+        let level = level_from_byte(frame[9]).ok_or_else(|| invalid_data("unknown level"))?;
+        let target_len = usize::from(u16::from_le_bytes([frame[10], frame[11]]));
+        let target_end = FRAME_HEADER_LEN + target_len;
+        if target_end > frame_len {
+            return Err(invalid_data("target longer than frame"));
+        }
+
+        let target = String::from_utf8(frame[FRAME_HEADER_LEN..target_end].to_vec())
+            .map_err(|_| invalid_data("target is not UTF-8"))?;
+        let message = String::from_utf8(frame[target_end..].to_vec())
+            .map_err(|_| invalid_data("message is not UTF-8"))?;
+
+        Ok(Some(BinaryRecord {
+            timestamp,
+            level,
+            target,
+            message,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = io::Result<BinaryRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let timestamp = DateTime::from_timestamp_micros(1_629_672_541_123_456).unwrap();
+        let mut buf: Vec<u8> = Vec::new();
+        for (level, message) in [(Level::Info, "first"), (Level::Trace, "ünïcode\nline")] {
+            write_frame(
+                &mut buf,
+                &Record::builder()
+                    .level(level)
+                    .target("rtl_sdr")
+                    .args(format_args!("ignored"))
+                    .build(),
+                timestamp,
+                message,
+            )
+            .unwrap();
+        }
+
+        let records: Vec<BinaryRecord> = BinaryReader::new(buf.as_slice())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].message, "first");
+        assert_eq!(records[0].level, Level::Info);
+        assert_eq!(records[1].timestamp, timestamp);
+        assert_eq!(records[1].level, Level::Trace);
+        assert_eq!(records[1].target, "rtl_sdr");
+        assert_eq!(records[1].message, "ünïcode\nline");
+
+        // a frame cut short is an error rather than a silently missing record
+        let mut reader = BinaryReader::new(&buf[..buf.len() - 1]);
+        assert!(reader.next().unwrap().is_ok());
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Logger sending records into a channel, for applications that show or
+//! forward them themselves
+
+use crate::format::{strip_styles, RecordFormatter};
+use crate::{fields, stats, ColorPreference, LoggingConfig};
+use log::{Level, Log, Metadata, Record};
+use std::sync::mpsc::{Sender, SyncSender};
+use std::sync::Mutex;
+
+/// A record as sent by `ChannelLogger`, both formatted and in parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LogLine {
+    pub level: Level,
+    pub target: String,
+    /// The message alone, without styling or key-values
+    pub message: String,
+    /// The key-values of the record and the global fields, with the values
+    /// as `LogFormat::Human` shows them
+    pub fields: Vec<(String, String)>,
+    /// The record as the format of the logger's config writes it, without
+    /// the line break at the end. Lines due ahead of the record, such as the
+    /// header or a status line, are part of it, on lines of their own.
+    pub line: String,
+}
+
+enum ChannelSender {
+    Bounded(SyncSender<LogLine>),
+    // `Sender` is not `Sync` before Rust 1.72
+    Unbounded(Mutex<Sender<LogLine>>),
+}
+
+/// A `log::Log` sending every record as a `LogLine`, e.g. to a UI thread
+/// that renders the log itself<br><br>
+/// Hand it to `LoggingConfig::set_extra_logger` to get the records along
+/// with this crate's output, or install it alone:<br>
+/// `let (sender, receiver) = std::sync::mpsc::sync_channel(1024);`<br>
+/// `config.set_extra_logger(Some(Arc::new(ChannelLogger::new(sender, LoggingConfig::new()))))`<br><br>
+/// The lines are formatted with the logger's own config, which also sets its
+/// level and module filter. Styling is removed unless that config's color
+/// preference is `ColorPreference::Always`, so a UI that renders ANSI codes
+/// gets them. Logging never blocks on the channel: a record that does not
+/// fit into a full bounded channel is dropped and counted as a write error,
+/// as is every record once the receiver is gone. As an extra logger, its
+/// lines take the sequence numbers of the outputs, and its records are not
+/// counted again in the stats and metrics, nor do they get status lines.
+pub struct ChannelLogger {
+    sender: ChannelSender,
+    config: LoggingConfig,
+    formatter: RecordFormatter,
+}
+
+impl ChannelLogger {
+    /// A logger sending into the bounded channel of `sender`, dropping
+    /// records while it is full
+    #[must_use]
+    pub fn new(sender: SyncSender<LogLine>, config: LoggingConfig) -> Self {
+        Self::with_sender(ChannelSender::Bounded(sender), config)
+    }
+
+    /// A logger sending into the unbounded channel of `sender`, which holds
+    /// every record until it is received
+    #[must_use]
+    pub fn unbounded(sender: Sender<LogLine>, config: LoggingConfig) -> Self {
+        Self::with_sender(ChannelSender::Unbounded(Mutex::new(sender)), config)
+    }
+
+    fn with_sender(sender: ChannelSender, config: LoggingConfig) -> Self {
+        Self {
+            sender,
+            formatter: RecordFormatter::new(config.clone()),
+            config,
+        }
+    }
+}
+
+impl Log for ChannelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        crate::force::enabled(&self.config, metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut line = Vec::new();
+        if self.formatter.write(&mut line, record).is_err() || line.is_empty() {
+            return;
+        }
+        let mut line = String::from_utf8_lossy(&line).into_owned();
+        if self.config.color() != ColorPreference::Always {
+            line = strip_styles(&line);
+        }
+        line.truncate(line.trim_end_matches('\n').len());
+        let line = LogLine {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            fields: fields::human_pairs(record.key_values()),
+            line,
+        };
+
+        let sent = match &self.sender {
+            // a full channel fails like a closed one, so logging never waits
+            ChannelSender::Bounded(sender) => sender.try_send(line).is_ok(),
+            ChannelSender::Unbounded(sender) => sender
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .send(line)
+                .is_ok(),
+        };
+        if !sent {
+            stats::count_write_error();
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LogFormat;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_channel_logger() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let logger = ChannelLogger::new(sender, LoggingConfig::new().set_format(LogFormat::Csv));
+        let fields = [("icao", "a1b2c3")];
+        logger.log(
+            &Record::builder()
+                .args(format_args!("tuned"))
+                .level(Level::Warn)
+                .target("app")
+                .key_values(&fields)
+                .build(),
+        );
+        let line = receiver.try_recv().unwrap();
+        assert_eq!(line.level, Level::Warn);
+        assert_eq!(line.target, "app");
+        assert_eq!(line.message, "tuned");
+        assert_eq!(
+            line.fields,
+            [(String::from("icao"), String::from("a1b2c3"))]
+        );
+        assert!(line.line.ends_with("Z,WARN,app,tuned,icao=a1b2c3"));
+
+        // the channel holds one line, the second is dropped
+        let before = stats::Stats::snapshot().write_errors;
+        for message in ["first", "second"] {
+            logger.log(&Record::builder().args(format_args!("{message}")).build());
+        }
+        assert_eq!(receiver.try_recv().unwrap().message, "first");
+        assert!(receiver.try_recv().is_err());
+        assert!(stats::Stats::snapshot().write_errors > before);
+
+        logger.log(
+            &Record::builder()
+                .args(format_args!("too verbose"))
+                .level(Level::Debug)
+                .build(),
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_unbounded_channel_logger() {
+        let (sender, receiver) = mpsc::channel();
+        let logger = ChannelLogger::unbounded(sender, LoggingConfig::new());
+        logger.log(&Record::builder().args(format_args!("tuned")).build());
+        let line = receiver.recv().unwrap();
+        assert!(line.line.starts_with("[INFO ]["));
+        assert!(line.line.ends_with("]tuned"));
+        assert!(!line.line.contains('\x1b'));
+    }
+}
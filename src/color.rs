@@ -0,0 +1,507 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use anstyle::{Ansi256Color, AnsiColor, Color, Effects, RgbColor, Style};
+use log::Level;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Whether the output is colored<br><br>
+/// With `Auto`, the decision is made at init from the environment, using the
+/// first rule that applies:
+/// 1. `NO_COLOR` set to any non-empty value disables color
+/// 2. `CLICOLOR_FORCE` set to anything other than `0` enables color, even when
+///    the output is not a terminal
+/// 3. `CLICOLOR=0` disables color
+/// 4. color is used if the output is a terminal
+///
+/// `Always` and `Never` ignore the environment and the terminal entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ColorPreference {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorPreference {
+    /// Decide whether to write color codes, reading the environment with `var`
+    pub(crate) fn resolve_with<F>(self, is_terminal: bool, var: F) -> bool
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        match self {
+            ColorPreference::Always => true,
+            ColorPreference::Never => false,
+            ColorPreference::Auto => {
+                if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+                    false
+                } else if var("CLICOLOR_FORCE")
+                    .is_some_and(|value| !value.is_empty() && value != "0")
+                {
+                    true
+                } else if var("CLICOLOR").is_some_and(|value| value == "0") {
+                    false
+                } else {
+                    is_terminal
+                }
+            }
+        }
+    }
+
+    /// Decide whether to write color codes from the process environment
+    pub(crate) fn resolve(self, is_terminal: bool) -> bool {
+        self.resolve_with(is_terminal, |name| env::var(name).ok())
+    }
+}
+
+/// The styles used for each field of `LogFormat::Human`<br><br>
+/// Set a field to `Style::new()` to leave it uncolored, for example to color
+/// the level but not the timestamp:<br>
+/// `let mut theme = ColorTheme::default(); theme.timestamp = Style::new();`<br><br>
+/// A theme can also be parsed from a spec, for config files and environment
+/// variables: `"info=green,bold; error=red; timestamp=#9f5001"`.parse().
+/// Entries are separated by `;` and name a field (`error`, `warn`, `info`,
+/// `debug`, `trace` or `timestamp`) and its style as a comma separated list of:
+/// - a color: a name (`black`, `red`, `green`, `yellow`, `blue`, `magenta`,
+///   `cyan`, `white`, or one of those prefixed with `bright_`), a 256-color
+///   index from `0` to `255`, or hex as `#rrggbb`
+/// - a background color: a color prefixed with `on_`, as in `on_blue`
+/// - an effect: `bold`, `dimmed`, `italic`, `underline`, `blink`, `reverse`,
+///   `hidden` or `strikethrough`
+/// - `plain`, for no style at all
+///
+/// Names are case insensitive. An entry replaces the whole style of its field
+/// and fields without an entry keep the default style. The spec may start with
+/// the name of a theme, see `ColorTheme::named`, to start from that theme
+/// instead of the default: `"solarized; error=bright_red"`. A name alone
+/// selects the theme, so one option can take either, e.g. `--theme colorblind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ColorTheme {
+    pub error: Style,
+    pub warn: Style,
+    pub info: Style,
+    pub debug: Style,
+    pub trace: Style,
+    pub timestamp: Style,
+}
+
+impl Default for ColorTheme {
+    /// Bold levels in red, yellow, green, cyan and magenta, and a bold orange timestamp
+    fn default() -> Self {
+        Self {
+            error: Style::new()
+                .fg_color(Some(Color::from(AnsiColor::Red)))
+                .bold(),
+            warn: Style::new()
+                .fg_color(Some(Color::from(AnsiColor::Yellow)))
+                .bold(),
+            info: Style::new()
+                .fg_color(Some(Color::from(AnsiColor::Green)))
+                .bold(),
+            debug: Style::new()
+                .fg_color(Some(Color::from(AnsiColor::Cyan)))
+                .bold(),
+            trace: Style::new()
+                .fg_color(Some(Color::from(AnsiColor::Magenta)))
+                .bold(),
+            timestamp: Style::new().fg_color(Some((159, 80, 1).into())).bold(),
+        }
+    }
+}
+
+/// The built-in themes as specs, see `ColorTheme::named`
+const BUILTIN_THEMES: [(&str, &str); 5] = [
+    ("default", ""),
+    (
+        "monochrome",
+        "error=bold,reverse; warn=bold,underline; info=bold; debug=plain; trace=dimmed; timestamp=dimmed",
+    ),
+    (
+        "high-contrast",
+        "error=bright_white,on_red,bold; warn=black,on_bright_yellow,bold; info=bright_green,bold; \
+         debug=bright_cyan,bold; trace=bright_magenta,bold; timestamp=bright_white,bold",
+    ),
+    (
+        "solarized",
+        "error=#dc322f,bold; warn=#b58900,bold; info=#859900,bold; debug=#2aa198,bold; \
+         trace=#6c71c4,bold; timestamp=#cb4b16",
+    ),
+    (
+        "colorblind",
+        "error=#d55e00,bold,reverse; warn=#e69f00,bold; info=#009e73,bold; debug=#56b4e9,bold; \
+         trace=#cc79a7,bold; timestamp=#0072b2",
+    ),
+];
+
+/// Themes added with `ColorTheme::register`, in the order they were added
+static REGISTERED_THEMES: RwLock<Vec<(String, ColorTheme)>> = RwLock::new(Vec::new());
+
+impl ColorTheme {
+    /// The theme called `name`, case insensitive, for options like `--theme`.
+    /// Themes added with `register` come first, so they can replace a built-in
+    /// one. The built-in themes are:
+    /// - `default`: `ColorTheme::default()`, bold red, yellow, green, cyan and
+    ///   magenta levels and a bold orange timestamp
+    /// - `monochrome`: no colors, for terminals without them. Error is bold and
+    ///   reversed, warn bold and underlined, info bold, debug plain, trace and
+    ///   the timestamp dimmed
+    /// - `high-contrast`: bold bright colors, with error in white on red and
+    ///   warn in black on yellow, and a bold bright white timestamp
+    /// - `solarized`: the bold Solarized accent colors, red `#dc322f`, yellow
+    ///   `#b58900`, green `#859900`, cyan `#2aa198` and violet `#6c71c4`, and an
+    ///   orange `#cb4b16` timestamp
+    /// - `colorblind`: the Okabe-Ito palette, which stays distinguishable with
+    ///   the common color vision deficiencies. Bold vermillion `#d55e00` error,
+    ///   also reversed so it does not rely on hue, orange `#e69f00` warn,
+    ///   bluish green `#009e73` info, sky blue `#56b4e9` debug, reddish purple
+    ///   `#cc79a7` trace and a blue `#0072b2` timestamp
+    ///
+    /// # Errors
+    /// A `ThemeError` if no theme is called `name`
+    pub fn named(name: &str) -> Result<Self, ThemeError> {
+        let name = name.trim().to_lowercase();
+        let registered = REGISTERED_THEMES
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((_, theme)) = registered.iter().find(|(known, _)| *known == name) {
+            return Ok(*theme);
+        }
+        match BUILTIN_THEMES.iter().find(|(known, _)| *known == name) {
+            Some((_, spec)) => spec.parse(),
+            None => Err(ThemeError(format!("unknown theme `{name}`"))),
+        }
+    }
+
+    /// Make `theme` available to `named` and theme specs as `name`, case
+    /// insensitive, replacing a theme registered earlier or a built-in theme
+    /// of that name. Registered themes are process wide.
+    pub fn register(name: &str, theme: ColorTheme) {
+        let name = name.trim().to_lowercase();
+        let mut registered = REGISTERED_THEMES
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match registered.iter_mut().find(|(known, _)| *known == name) {
+            Some(entry) => entry.1 = theme,
+            None => registered.push((name, theme)),
+        }
+    }
+
+    /// The names accepted by `named`, the built-in themes first, e.g. for the
+    /// help text of a `--theme` option
+    #[must_use]
+    pub fn names() -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_THEMES
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        let registered = REGISTERED_THEMES
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (name, _) in registered.iter() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    /// The style for the level field of `level`
+    #[must_use]
+    pub fn level(&self, level: Level) -> Style {
+        match level {
+            Level::Error => self.error,
+            Level::Warn => self.warn,
+            Level::Info => self.info,
+            Level::Debug => self.debug,
+            Level::Trace => self.trace,
+        }
+    }
+}
+
+/// The theme and color switch of a running logger, shared by its outputs and
+/// changed through `LoggingGuard`
+#[derive(Debug)]
+pub(crate) struct LiveTheme {
+    theme: RwLock<ColorTheme>,
+    color: AtomicBool,
+}
+
+impl LiveTheme {
+    pub(crate) fn new(theme: ColorTheme) -> Self {
+        Self {
+            theme: RwLock::new(theme),
+            color: AtomicBool::new(true),
+        }
+    }
+
+    pub(crate) fn theme(&self) -> ColorTheme {
+        *self
+            .theme
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(crate) fn set_theme(&self, theme: ColorTheme) {
+        *self
+            .theme
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = theme;
+    }
+
+    pub(crate) fn color(&self) -> bool {
+        self.color.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_color(&self, color: bool) {
+        self.color.store(color, Ordering::Relaxed);
+    }
+}
+
+/// A theme spec that could not be parsed, see `ColorTheme`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeError(String);
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid theme spec: {}", self.0)
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// Parse a color name, 256-color index or `#rrggbb` hex color
+fn parse_color(spec: &str) -> Option<Color> {
+    let (bright, name) = match spec.strip_prefix("bright_") {
+        Some(name) => (true, name),
+        None => (false, spec),
+    };
+    let ansi = match name {
+        "black" => Some(AnsiColor::Black),
+        "red" => Some(AnsiColor::Red),
+        "green" => Some(AnsiColor::Green),
+        "yellow" => Some(AnsiColor::Yellow),
+        "blue" => Some(AnsiColor::Blue),
+        "magenta" => Some(AnsiColor::Magenta),
+        "cyan" => Some(AnsiColor::Cyan),
+        "white" => Some(AnsiColor::White),
+        _ => None,
+    };
+    if let Some(ansi) = ansi {
+        return Some(Color::from(ansi.bright(bright)));
+    }
+    if bright {
+        return None;
+    }
+    if let Some(hex) = spec.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+        return Some(Color::from(RgbColor(channel(0)?, channel(2)?, channel(4)?)));
+    }
+    spec.parse::<u8>()
+        .ok()
+        .map(|index| Color::from(Ansi256Color(index)))
+}
+
+/// Parse the comma separated style of one theme entry
+fn parse_style(spec: &str) -> Result<Style, ThemeError> {
+    let mut style = Style::new();
+    for part in spec.split(',').map(str::trim) {
+        let effect = match part {
+            "plain" => Some(Effects::new()),
+            "bold" => Some(Effects::BOLD),
+            "dimmed" => Some(Effects::DIMMED),
+            "italic" => Some(Effects::ITALIC),
+            "underline" => Some(Effects::UNDERLINE),
+            "blink" => Some(Effects::BLINK),
+            "reverse" => Some(Effects::INVERT),
+            "hidden" => Some(Effects::HIDDEN),
+            "strikethrough" => Some(Effects::STRIKETHROUGH),
+            _ => None,
+        };
+        style = if let Some(effect) = effect {
+            style.effects(style.get_effects() | effect)
+        } else if let Some(background) = part.strip_prefix("on_") {
+            let color = parse_color(background)
+                .ok_or_else(|| ThemeError(format!("unknown background color `{background}`")))?;
+            style.bg_color(Some(color))
+        } else {
+            let color =
+                parse_color(part).ok_or_else(|| ThemeError(format!("unknown style `{part}`")))?;
+            style.fg_color(Some(color))
+        };
+    }
+    Ok(style)
+}
+
+impl FromStr for ColorTheme {
+    type Err = ThemeError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut theme = ColorTheme::default();
+        for (index, entry) in spec
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+        {
+            let entry = entry.to_lowercase();
+            if index == 0 && !entry.contains('=') {
+                theme = ColorTheme::named(&entry)?;
+                continue;
+            }
+            let (field, style) = entry
+                .split_once('=')
+                .ok_or_else(|| ThemeError(format!("expected `field=style` in `{entry}`")))?;
+            let style = parse_style(style)?;
+            match field.trim() {
+                "error" => theme.error = style,
+                "warn" => theme.warn = style,
+                "info" => theme.info = style,
+                "debug" => theme.debug = style,
+                "trace" => theme.trace = style,
+                "timestamp" => theme.timestamp = style,
+                field => return Err(ThemeError(format!("unknown field `{field}`"))),
+            }
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resolve(preference: ColorPreference, is_terminal: bool, vars: &[(&str, &str)]) -> bool {
+        preference.resolve_with(is_terminal, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_color_precedence() {
+        use ColorPreference::{Always, Auto, Never};
+
+        // no environment, follow the terminal
+        assert!(resolve(Auto, true, &[]));
+        assert!(!resolve(Auto, false, &[]));
+
+        // NO_COLOR beats everything in the environment
+        assert!(!resolve(Auto, true, &[("NO_COLOR", "1")]));
+        assert!(!resolve(
+            Auto,
+            true,
+            &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]
+        ));
+        assert!(resolve(Auto, true, &[("NO_COLOR", "")]));
+
+        // CLICOLOR_FORCE beats CLICOLOR and the terminal check
+        assert!(resolve(Auto, false, &[("CLICOLOR_FORCE", "1")]));
+        assert!(resolve(
+            Auto,
+            false,
+            &[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")]
+        ));
+        assert!(!resolve(Auto, false, &[("CLICOLOR_FORCE", "0")]));
+
+        // CLICOLOR=0 disables, other values defer to the terminal
+        assert!(!resolve(Auto, true, &[("CLICOLOR", "0")]));
+        assert!(resolve(Auto, true, &[("CLICOLOR", "1")]));
+        assert!(!resolve(Auto, false, &[("CLICOLOR", "1")]));
+
+        // explicit preferences ignore the environment
+        assert!(resolve(Always, false, &[("NO_COLOR", "1")]));
+        assert!(!resolve(Never, true, &[("CLICOLOR_FORCE", "1")]));
+    }
+
+    #[test]
+    fn test_theme_spec() {
+        let theme: ColorTheme =
+            "info=green,bold; error=Red; timestamp=#9f5001; trace=208,on_bright_blue"
+                .parse()
+                .unwrap();
+        let default = ColorTheme::default();
+        assert_eq!(theme.info, default.info);
+        assert_eq!(theme.warn, default.warn);
+        assert_eq!(
+            theme.error,
+            Style::new().fg_color(Some(Color::from(AnsiColor::Red)))
+        );
+        assert_eq!(
+            theme.timestamp,
+            Style::new().fg_color(Some((159, 80, 1).into()))
+        );
+        assert_eq!(
+            theme.trace,
+            Style::new()
+                .fg_color(Some(Color::from(Ansi256Color(208))))
+                .bg_color(Some(Color::from(AnsiColor::BrightBlue)))
+        );
+        assert_eq!(
+            "debug=plain".parse::<ColorTheme>().unwrap().debug,
+            Style::new()
+        );
+        assert_eq!("".parse::<ColorTheme>().unwrap(), default);
+
+        for invalid in [
+            "info=purple",
+            "info=#12345",
+            "info=#gggggg",
+            "info=256",
+            "info=bright_256",
+            "info=on_nothing",
+            "level=red",
+            "info",
+        ] {
+            assert!(invalid.parse::<ColorTheme>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_named_themes() {
+        for name in ColorTheme::names() {
+            assert!(ColorTheme::named(&name).is_ok(), "{name}");
+        }
+        assert_eq!(ColorTheme::named("Default").unwrap(), ColorTheme::default());
+        assert_eq!(ColorTheme::named("monochrome").unwrap().debug, Style::new());
+        assert!(ColorTheme::named("neon").is_err());
+
+        // a spec can start from a named theme
+        let theme: ColorTheme = "solarized; error=red".parse().unwrap();
+        assert_eq!(theme.info, ColorTheme::named("solarized").unwrap().info);
+        assert_eq!(
+            theme.error,
+            Style::new().fg_color(Some(Color::from(AnsiColor::Red)))
+        );
+        assert!("error=red; solarized".parse::<ColorTheme>().is_err());
+
+        let custom: ColorTheme = "info=blue".parse().unwrap();
+        ColorTheme::register("Station", custom);
+        assert_eq!(ColorTheme::named("station").unwrap(), custom);
+        assert_eq!("station".parse::<ColorTheme>().unwrap(), custom);
+        assert!(ColorTheme::names().contains(&String::from("station")));
+    }
+}
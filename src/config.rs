@@ -0,0 +1,1973 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use crate::color::LiveTheme;
+use crate::filter::LiveFilter;
+use crate::recent::RecentLines;
+use crate::{ColorPreference, ColorTheme, LoggingGuard, ModuleFilter};
+use anstyle::Style;
+use chrono::{DateTime, FixedOffset, Local, Offset, SecondsFormat, Utc};
+use log::{Level, LevelFilter};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Timezone information appended to local timestamps.<br><br>
+/// chrono does not know zone abbreviations (`PDT`, `CET`) for the system
+/// timezone, so the zone is identified by its UTC offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TimezoneSuffix {
+    /// No suffix. `2021-08-22T15:49:01`
+    #[default]
+    None,
+    /// The UTC offset. `2021-08-22T15:49:01-07:00`
+    Offset,
+}
+
+/// The number of fractional second digits in the timestamps of structured formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TimePrecision {
+    /// Whole seconds. `2021-08-22T22:49:01Z`
+    Seconds,
+    /// 3 digits. `2021-08-22T22:49:01.123Z`
+    #[default]
+    Millis,
+    /// 6 digits. `2021-08-22T22:49:01.123456Z`
+    Micros,
+    /// 9 digits. `2021-08-22T22:49:01.123456789Z`
+    Nanos,
+}
+
+impl TimePrecision {
+    pub(crate) fn seconds_format(self) -> SecondsFormat {
+        match self {
+            Self::Seconds => SecondsFormat::Secs,
+            Self::Millis => SecondsFormat::Millis,
+            Self::Micros => SecondsFormat::Micros,
+            Self::Nanos => SecondsFormat::Nanos,
+        }
+    }
+
+    /// The number of fractional second digits
+    pub(crate) fn digits(self) -> usize {
+        match self {
+            Self::Seconds => 0,
+            Self::Millis => 3,
+            Self::Micros => 6,
+            Self::Nanos => 9,
+        }
+    }
+}
+
+/// What is written for a record whose message is empty or only whitespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EmptyMessage {
+    /// The line is written with an empty message. `[INFO ][2021-08-22T15:49:01]`
+    #[default]
+    Keep,
+    /// The record is not written, and counted as filtered
+    Drop,
+    /// `<empty>` is written as the message. `[INFO ][2021-08-22T15:49:01]<empty>`
+    Placeholder,
+}
+
+/// What `LoggingGuard::resume` does with the records held while the console
+/// was paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ResumePolicy {
+    /// The held records are written, in the order they were logged
+    #[default]
+    Flush,
+    /// The records are thrown away, and counted in a line written on resume
+    Discard,
+}
+
+/// How the level is labelled in `LogFormat::Human`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LevelLabel {
+    /// The full level name, padded to 5 characters. `INFO `
+    #[default]
+    Full,
+    /// The first letter of the level name. `I`
+    Short,
+}
+
+/// The letter case of the level label in `LogFormat::Human`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LevelCase {
+    /// `INFO`
+    Upper,
+    /// `info`, as in logfmt
+    Lower,
+    /// `Info`
+    Title,
+}
+
+/// The layout of each log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LogFormat {
+    /// The colored, bracketed format. `[INFO ][2021-08-22T15:49:01]message`
+    #[default]
+    Human,
+    /// One JSON object per line, using the field names of Vector's log schema
+    /// so it can be ingested without a remap transform.<br>
+    /// `{"timestamp":"2021-08-22T22:49:01.123Z","host":"receiver","level":"INFO","target":"app","message":"message"}`<br><br>
+    /// `timestamp` is always RFC3339 in UTC. `host` is the hostname, see `LoggingConfig::set_hostname`.
+    Json,
+    /// Compact length prefixed frames for high volume logging, to be decoded
+    /// later with `BinaryReader`. See the `binary` module for the layout. Meant
+    /// for `LogTarget::File`, as the output is not readable on a terminal.
+    Binary,
+    /// Comma separated values with RFC 4180 quoting, for spreadsheets and
+    /// pandas.<br>
+    /// `2021-08-22T22:49:01.123Z,INFO,app,message,icao=a1b2c3`<br><br>
+    /// The columns are `timestamp` (RFC3339 in UTC), `level`, `target`, `message`
+    /// and `fields`, the key-values as `key=value` pairs. Fields containing commas,
+    /// quotes or line breaks are quoted. Lines end with `\n`.
+    Csv,
+    /// Apache Common Log Format for HTTP access records, as written by web
+    /// servers.<br>
+    /// `192.0.2.7 - - [22/Aug/2021:15:49:01 -0700] "GET /status HTTP/1.1" 200 512`<br><br>
+    /// Records need the key-values `method`, `path`, `status` and `bytes`;
+    /// `remote` (the client address), `user` and `protocol` (`HTTP/1.1` if
+    /// missing) are optional. With a `referer` or `user_agent` key-value the
+    /// line is in the combined format, with both appended. The message is not
+    /// part of the line. Records without the required key-values, such as
+    /// ordinary log lines, are written as in `LogFormat::Human`.
+    CommonLog,
+    /// Fixed width columns separated by a space, for `cut -c` and aligned
+    /// tables.<br>
+    /// `INFO  2021-08-22T15:49:01 acars_router::net     message`<br><br>
+    /// The columns are the level, the timestamp in the `set_time_format`
+    /// format, the target and the message followed by its key-values, with
+    /// the widths of `set_column_widths`. Shorter values are padded with
+    /// spaces and longer ones are cut at the width without a marker, counting
+    /// characters. Lines are not colored.
+    Columns,
+    /// The human format exactly as the 0.3 releases wrote it by default, for
+    /// parsers that must not see a change when the default evolves.<br>
+    /// `[INFO ][2021-08-22T15:49:01]message`<br><br>
+    /// The level is padded to 5 characters and the local timestamp always uses
+    /// `%Y-%m-%dT%H:%M:%S`, styled with the default theme when colored. Every
+    /// option that changes the line, such as the theme, level labels, time
+    /// format, key-values, sequence numbers or message limits, is ignored;
+    /// filters, sampling and the color preference still apply.
+    LegacyV1,
+}
+
+/// The widths in characters of the columns of `LogFormat::Columns`<br><br>
+/// `let mut widths = ColumnWidths::default(); widths.target = 32;`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ColumnWidths {
+    pub level: usize,
+    pub timestamp: usize,
+    pub target: usize,
+    /// `None` leaves the message, the last column, as long as it is
+    pub message: Option<usize>,
+}
+
+impl Default for ColumnWidths {
+    /// Room for the level names, the default timestamp and a short module path
+    fn default() -> Self {
+        Self {
+            level: 5,
+            timestamp: 19,
+            target: 24,
+            message: None,
+        }
+    }
+}
+
+/// Where the output is written
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LogTarget {
+    #[default]
+    Stderr,
+    Stdout,
+    /// Append to the file at this path, creating it if needed
+    File(PathBuf),
+}
+
+/// An additional output with its own format and color policy, added with
+/// `LoggingConfig::add_sink`<br><br>
+/// `Sink::new(LogTarget::File("app.json".into())).set_format(LogFormat::Json)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sink {
+    target: LogTarget,
+    format: LogFormat,
+    color: ColorPreference,
+    show_detail: bool,
+}
+
+impl Sink {
+    /// A sink writing `LogFormat::Human` to `target`, colored by `ColorPreference::Auto`
+    #[must_use]
+    pub fn new(target: LogTarget) -> Self {
+        Self {
+            target,
+            format: LogFormat::Human,
+            color: ColorPreference::Auto,
+            show_detail: true,
+        }
+    }
+
+    /// Set the format of this sink. Defaults to `LogFormat::Human`.
+    #[must_use]
+    pub fn set_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set whether this sink is colored. Defaults to `ColorPreference::Auto`.
+    #[must_use]
+    pub fn set_color(mut self, color: ColorPreference) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set whether this sink writes the `detail` key-value, see
+    /// `LoggingConfig::set_show_detail`. Defaults to `true`.
+    #[must_use]
+    pub fn set_show_detail(mut self, show: bool) -> Self {
+        self.show_detail = show;
+        self
+    }
+
+    #[must_use]
+    pub fn target(&self) -> &LogTarget {
+        &self.target
+    }
+
+    #[must_use]
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    #[must_use]
+    pub fn color(&self) -> ColorPreference {
+        self.color
+    }
+
+    #[must_use]
+    pub fn show_detail(&self) -> bool {
+        self.show_detail
+    }
+}
+
+/// A label shown instead of the level for records of one target
+#[derive(Debug, Clone)]
+struct TargetLabel {
+    target: String,
+    label: String,
+    style: Style,
+}
+
+/// A user supplied logger fed every record, see `LoggingConfig::set_extra_logger`
+#[derive(Clone)]
+struct ExtraLogger(Arc<dyn log::Log>);
+
+impl fmt::Debug for ExtraLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ExtraLogger")
+    }
+}
+
+/// The closure given to `LoggingConfig::set_builder_hook`
+#[derive(Clone)]
+struct BuilderHook(Arc<dyn Fn(&mut env_logger::Builder) + Send + Sync>);
+
+impl fmt::Debug for BuilderHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BuilderHook")
+    }
+}
+
+/// The closure given to `LoggingConfig::set_clock`
+#[derive(Clone)]
+struct Clock(Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>);
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+/// Configuration used to initialize logging<br><br>
+/// The defaults produce the same output as `enable_logging`. Options are set
+/// by chaining the `set_*` methods and logging is started with `init`.
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    level: LevelFilter,
+    timezone_suffix: TimezoneSuffix,
+    max_message_length: Option<usize>,
+    format: LogFormat,
+    hostname: Option<String>,
+    escape_control_characters: bool,
+    strip_message_ansi: bool,
+    time_format: String,
+    level_label: LevelLabel,
+    brackets: bool,
+    timestamp_first: bool,
+    color: ColorPreference,
+    theme: ColorTheme,
+    target: LogTarget,
+    sequence_numbers: bool,
+    date_time_separator: char,
+    collapse_repeats: bool,
+    level_names: [String; 5],
+    summary_on_drop: bool,
+    dual_timestamps: bool,
+    sample_rates: [u64; 5],
+    level_cooldowns: [Option<Duration>; 5],
+    header: bool,
+    deny_messages: Vec<String>,
+    min_message_length: usize,
+    slow_log_threshold: Option<Duration>,
+    slow_log_keep_untimed: bool,
+    target_labels: Vec<TargetLabel>,
+    level_targets: [Option<LogTarget>; 5],
+    time_deltas: bool,
+    time_precision: TimePrecision,
+    level_time_precisions: [Option<TimePrecision>; 5],
+    wrap_column: Option<usize>,
+    extra_logger: Option<ExtraLogger>,
+    field_count: bool,
+    drop_summary_interval: Option<Duration>,
+    json_pretty: bool,
+    level_case: Option<LevelCase>,
+    builder_hook: Option<BuilderHook>,
+    json_timestamp: bool,
+    color_min_level: Level,
+    level_border: Option<char>,
+    banners: Option<(String, String)>,
+    empty_message: EmptyMessage,
+    sinks: Vec<Sink>,
+    module_filter: ModuleFilter,
+    write_error_fallback: bool,
+    column_widths: ColumnWidths,
+    rate_status_interval: Option<Duration>,
+    rate_status_window: Duration,
+    write_latency_interval: Option<Duration>,
+    heartbeat_interval: Option<Duration>,
+    heartbeat_level: Level,
+    pause_capacity: usize,
+    resume_policy: ResumePolicy,
+    rfc3339_timestamps: bool,
+    utc_timestamps: bool,
+    expected_utc_offset: Option<FixedOffset>,
+    utc_offset_tolerance: Duration,
+    uptime: bool,
+    aligned_fields: Option<usize>,
+    shown_fields: Vec<String>,
+    init_level: Option<LevelFilter>,
+    utf8_bom: bool,
+    elapsed_time: bool,
+    epoch_width: Option<usize>,
+    recent_log_capacity: usize,
+    clock: Option<Clock>,
+    /// Set by `init`, for the changes made through `LoggingGuard`
+    live_theme: Option<Arc<LiveTheme>>,
+    /// Set by `init`, for `LoggingGuard::set_module_filter`
+    live_filter: Option<Arc<LiveFilter>>,
+    /// Set by `init` with `set_recent_log_capacity`, for `recent_logs`
+    recent_lines: Option<Arc<RecentLines>>,
+    show_level: bool,
+    highlights: Vec<(String, Style)>,
+    collapse_backtraces: bool,
+    show_detail: bool,
+    #[cfg(feature = "android")]
+    android_tag: Option<String>,
+    #[cfg(feature = "wasm")]
+    browser_console: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            timezone_suffix: TimezoneSuffix::None,
+            max_message_length: None,
+            format: LogFormat::Human,
+            hostname: None,
+            escape_control_characters: false,
+            strip_message_ansi: false,
+            time_format: String::from(DEFAULT_TIME_FORMAT),
+            level_label: LevelLabel::Full,
+            brackets: true,
+            timestamp_first: false,
+            color: ColorPreference::Auto,
+            theme: ColorTheme::default(),
+            target: LogTarget::Stderr,
+            sequence_numbers: false,
+            date_time_separator: 'T',
+            collapse_repeats: false,
+            level_names: [
+                Level::Error,
+                Level::Warn,
+                Level::Info,
+                Level::Debug,
+                Level::Trace,
+            ]
+            .map(|level| level.as_str().to_string()),
+            summary_on_drop: false,
+            dual_timestamps: false,
+            sample_rates: [1; 5],
+            level_cooldowns: [None; 5],
+            header: false,
+            deny_messages: Vec::new(),
+            min_message_length: 0,
+            slow_log_threshold: None,
+            slow_log_keep_untimed: true,
+            target_labels: Vec::new(),
+            level_targets: Default::default(),
+            time_deltas: false,
+            time_precision: TimePrecision::Millis,
+            level_time_precisions: [None; 5],
+            wrap_column: None,
+            extra_logger: None,
+            field_count: false,
+            drop_summary_interval: None,
+            json_pretty: false,
+            level_case: None,
+            builder_hook: None,
+            json_timestamp: true,
+            color_min_level: Level::Trace,
+            level_border: None,
+            banners: None,
+            empty_message: EmptyMessage::Keep,
+            sinks: Vec::new(),
+            module_filter: ModuleFilter::new(),
+            write_error_fallback: false,
+            column_widths: ColumnWidths::default(),
+            rate_status_interval: None,
+            rate_status_window: Duration::from_secs(10),
+            write_latency_interval: None,
+            heartbeat_interval: None,
+            heartbeat_level: Level::Info,
+            pause_capacity: 1000,
+            resume_policy: ResumePolicy::Flush,
+            rfc3339_timestamps: false,
+            utc_timestamps: false,
+            expected_utc_offset: None,
+            utc_offset_tolerance: Duration::ZERO,
+            uptime: false,
+            aligned_fields: None,
+            shown_fields: Vec::new(),
+            init_level: None,
+            utf8_bom: false,
+            elapsed_time: false,
+            epoch_width: None,
+            recent_log_capacity: 0,
+            clock: None,
+            live_theme: None,
+            live_filter: None,
+            recent_lines: None,
+            show_level: true,
+            highlights: Vec::new(),
+            collapse_backtraces: false,
+            show_detail: true,
+            #[cfg(feature = "android")]
+            android_tag: Some(String::from("sdre-rust-logging")),
+            #[cfg(feature = "wasm")]
+            browser_console: true,
+        }
+    }
+}
+
+impl LoggingConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A preset for narrow terminals and small displays. Only the time and the
+    /// first letter of the level are shown, without brackets.<br>
+    /// `15:49:01 I This is an info message`<br><br>
+    /// The message follows 11 characters: 8 of time, the level letter and two
+    /// spaces.
+    #[must_use]
+    pub fn compact() -> Self {
+        Self::default()
+            .set_time_format("%H:%M:%S")
+            .set_level_label(LevelLabel::Short)
+            .set_brackets(false)
+            .set_timestamp_first(true)
+    }
+
+    /// A preset for character displays, such as the 16 or 20 column screens
+    /// of SDR receivers. Like `compact`, with the minutes and seconds since
+    /// logging was initialized in place of the time, see `set_elapsed_time`.<br>
+    /// `01:02 I This is an info message`<br><br>
+    /// The message follows 8 characters: 5 of elapsed time, the level letter
+    /// and two spaces. From 100 minutes on, the time takes one more character
+    /// per extra digit of minutes.
+    #[must_use]
+    pub fn tiny() -> Self {
+        Self::compact().set_elapsed_time(true)
+    }
+
+    /// A preset for development under a harness: NDJSON records on stdout
+    /// for the harness to capture, and the usual human lines on stderr for
+    /// the developer, colored when stderr is a terminal. Both get every
+    /// record, as they share the level and filters. Options set afterwards
+    /// apply to both outputs, except `set_format` and `set_color`, which only
+    /// change the stderr lines.
+    #[must_use]
+    pub fn split_streams() -> Self {
+        Self::default().set_target(LogTarget::Stderr).add_sink(
+            Sink::new(LogTarget::Stdout)
+                .set_format(LogFormat::Json)
+                .set_color(ColorPreference::Never),
+        )
+    }
+
+    /// Set the logging level. Defaults to `LevelFilter::Info`.
+    #[must_use]
+    pub fn set_level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the level of the startup phase, which lasts until `finish_init` is
+    /// called, e.g. `Some(LevelFilter::Debug)` to see the details of a heavy
+    /// device setup and then continue at the level of `set_level`. The init
+    /// level replaces the default level only; targets with a directive in
+    /// `set_module_filter` keep their level in both phases.<br><br>
+    /// The transition happens once per process: the first `finish_init` call
+    /// switches to the steady level for every record logged after it, and
+    /// later calls do nothing. If `finish_init` runs before `init`, the init
+    /// phase is skipped. The init level may also be quieter than the steady
+    /// one, e.g. to keep a noisy startup to warnings. Defaults to `None`, no
+    /// init phase.
+    #[must_use]
+    pub fn set_init_level(mut self, level: Option<LevelFilter>) -> Self {
+        self.init_level = level;
+        self
+    }
+
+    /// Set levels per module, e.g. `"receiver::rf=trace".parse()` to trace one
+    /// module while the rest stays at the `set_level` level. A default level in
+    /// the filter replaces `set_level`. The filter applies to every output,
+    /// including the sinks of `add_sink` and `set_level_target`, and can be
+    /// replaced at runtime with `LoggingGuard::set_module_filter`. Defaults to
+    /// `ModuleFilter::new()`, without directives.
+    #[must_use]
+    pub fn set_module_filter(mut self, filter: ModuleFilter) -> Self {
+        self.module_filter = filter;
+        self
+    }
+
+    /// Set the timezone information appended to timestamps. Defaults to `TimezoneSuffix::None`.
+    #[must_use]
+    pub fn set_timezone_suffix(mut self, suffix: TimezoneSuffix) -> Self {
+        self.timezone_suffix = suffix;
+        self
+    }
+
+    /// Set the maximum length of a message in bytes. Longer messages are cut
+    /// short with an ellipsis and a note of how many bytes were dropped. The
+    /// limit applies to the message as written, after `set_strip_message_ansi`
+    /// and `set_escape_control_characters`, and the cut never splits an ANSI
+    /// escape sequence. The prefix fields do not count towards the limit.
+    /// Defaults to `None` (unlimited).
+    #[must_use]
+    pub fn set_max_message_length(mut self, max_length: Option<usize>) -> Self {
+        self.max_message_length = max_length;
+        self
+    }
+
+    /// Set the output format. Defaults to `LogFormat::Human`.
+    #[must_use]
+    pub fn set_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the hostname reported in the `host` field of structured formats.
+    /// Defaults to `None`, which looks up the hostname of the machine at init.
+    #[must_use]
+    pub fn set_hostname(mut self, hostname: Option<String>) -> Self {
+        self.hostname = hostname;
+        self
+    }
+
+    /// Set whether `LogFormat::Json` objects are indented over several lines,
+    /// one member per line and nested objects and arrays a level further, for
+    /// reading during local debugging. This breaks the one record per line
+    /// layout that log shippers rely on, so only use it interactively.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn set_json_pretty(mut self, pretty: bool) -> Self {
+        self.json_pretty = pretty;
+        self
+    }
+
+    /// Set the widths of the columns of `LogFormat::Columns`. Defaults to
+    /// `ColumnWidths::default()`.
+    #[must_use]
+    pub fn set_column_widths(mut self, widths: ColumnWidths) -> Self {
+        self.column_widths = widths;
+        self
+    }
+
+    /// Set whether `LogFormat::Json` objects start with the `timestamp` field,
+    /// the time the record was logged as RFC3339 in UTC, e.g.
+    /// `"2021-08-22T22:49:01.123Z"`, with the precision of `set_time_precision`.
+    /// Turn it off when the ingestion pipeline stamps the arrival time itself;
+    /// the `local_timestamp` field of `set_dual_timestamps` is left out too.
+    /// Only JSON is affected. Defaults to `true`.
+    #[must_use]
+    pub fn set_json_timestamp(mut self, timestamp: bool) -> Self {
+        self.json_timestamp = timestamp;
+        self
+    }
+
+    /// Replace control characters in messages (newlines, escape sequences, NUL...)
+    /// with their escaped form so they can not corrupt the terminal or split a
+    /// record across lines. `LogFormat::Json` always escapes them, as raw control
+    /// characters are invalid JSON, so this setting does not change JSON output.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn set_escape_control_characters(mut self, escape: bool) -> Self {
+        self.escape_control_characters = escape;
+        self
+    }
+
+    /// Remove ANSI escape sequences found in messages, such as the colors of
+    /// echoed subprocess output, so a message can not restyle the terminal or
+    /// move its cursor. This is independent of the crate's own coloring and of
+    /// `set_color`. Other control characters are kept, or escaped along with
+    /// `set_escape_control_characters`. Defaults to `false`.
+    #[must_use]
+    pub fn set_strip_message_ansi(mut self, strip: bool) -> Self {
+        self.strip_message_ansi = strip;
+        self
+    }
+
+    /// Set the chrono format string used for timestamps in `LogFormat::Human`.
+    /// The timezone suffix, if any, is appended to it. An invalid format string
+    /// is replaced by the default when logging is initialized. Defaults to `%Y-%m-%dT%H:%M:%S`.
+    #[must_use]
+    pub fn set_time_format(mut self, format: &str) -> Self {
+        self.time_format = format.to_string();
+        self
+    }
+
+    /// Set whether human and column timestamps are strict RFC3339, as
+    /// `2021-08-22T15:49:01.123-07:00`, or `2021-08-22T22:49:01.123Z` when the
+    /// local timezone is UTC. The default time format has no timezone
+    /// designator, which strict RFC3339 and ISO 8601 validators reject, and is
+    /// kept as the default for compatibility. The timestamp is written by
+    /// chrono's `to_rfc3339_opts` with the digits of `set_time_precision`, and
+    /// replaces the time format, the date time separator and the timezone
+    /// suffix. `LogFormat::Columns` cuts it to `ColumnWidths::timestamp`, so
+    /// widen that column, e.g. to 29 for milliseconds and an offset. Defaults
+    /// to `false`.
+    #[must_use]
+    pub fn set_rfc3339_timestamps(mut self, rfc3339: bool) -> Self {
+        self.rfc3339_timestamps = rfc3339;
+        self
+    }
+
+    /// Set whether human, column and common log timestamps are in UTC instead
+    /// of the local timezone, for hosts and containers whose logs are compared
+    /// across timezones. The time format is unchanged, so
+    /// `TimezoneSuffix::Offset` appends `+00:00` and `set_rfc3339_timestamps`
+    /// writes a `Z`. Ignored with `set_dual_timestamps`, which already shows
+    /// both. JSON and CSV timestamps are always UTC. Defaults to `false`.
+    #[must_use]
+    pub fn set_utc_timestamps(mut self, utc: bool) -> Self {
+        self.utc_timestamps = utc;
+        self
+    }
+
+    /// Set the UTC offset the local timezone of the host is expected to have,
+    /// e.g. `FixedOffset::east_opt(0)` for a fleet of receivers that run on
+    /// UTC. When the local offset at init differs from it by more than
+    /// `set_utc_offset_tolerance`, `init` logs a single warning naming both,
+    /// so a host with a misconfigured timezone is noticed before its
+    /// timestamps are compared with the others. Zones with daylight saving
+    /// time change their offset during the year, so either expect the current
+    /// one or allow an hour of tolerance. Only the timezone is checked, not
+    /// the clock itself. Defaults to `None` (no check).
+    #[must_use]
+    pub fn set_expected_utc_offset(mut self, offset: Option<FixedOffset>) -> Self {
+        self.expected_utc_offset = offset;
+        self
+    }
+
+    /// Set how far the local UTC offset may be from the one of
+    /// `set_expected_utc_offset` without a warning. Defaults to
+    /// `Duration::ZERO`, an exact match.
+    #[must_use]
+    pub fn set_utc_offset_tolerance(mut self, tolerance: Duration) -> Self {
+        self.utc_offset_tolerance = tolerance;
+        self
+    }
+
+    /// Set the character between the date and the time, e.g. `' '` for
+    /// `2021-08-22 15:49:01`. Only applies to the default time format; a custom
+    /// format from `set_time_format` is used as is. Defaults to the ISO 8601 `'T'`.
+    #[must_use]
+    pub fn set_date_time_separator(mut self, separator: char) -> Self {
+        self.date_time_separator = separator;
+        self
+    }
+
+    /// Set how the level is labelled. Defaults to `LevelLabel::Full`.
+    #[must_use]
+    pub fn set_level_label(mut self, label: LevelLabel) -> Self {
+        self.level_label = label;
+        self
+    }
+
+    /// Set the letter case of the level label in `LogFormat::Human`. The case
+    /// is applied to the label as it is shown, so custom names from
+    /// `set_level_name` and the labels from `set_target_label` are recased
+    /// too, before `LevelLabel::Short` takes the first character. Structured
+    /// formats keep the standard level names. Defaults to showing the labels
+    /// as they are, which is uppercase for the standard level names.
+    #[must_use]
+    pub fn set_level_case(mut self, case: LevelCase) -> Self {
+        self.level_case = Some(case);
+        self
+    }
+
+    /// Set the label shown for `level` in `LogFormat::Human`, e.g. `"NOTE"` for
+    /// info or a translation. With `LevelLabel::Full` every label is padded to
+    /// the length of the longest one, so the column stays aligned; with
+    /// `LevelLabel::Short` the first character of the label is used. Structured
+    /// formats keep the standard level names. Defaults to the English level names.
+    #[must_use]
+    pub fn set_level_name(mut self, level: Level, name: &str) -> Self {
+        self.level_names[level as usize - 1] = name.to_string();
+        self
+    }
+
+    /// Add a highlight rule: every occurrence of `pattern` in the message of a
+    /// human line is styled with `style`, like `grep --color`, e.g.
+    /// `.add_highlight("a1b2c3", Style::new().reverse())` to spot a callsign.
+    /// Patterns are plain, case sensitive substrings. Where rules overlap, the
+    /// earliest match wins, and the first added rule among matches at the same
+    /// place. With any rule set, every message is rendered to a string and
+    /// scanned once per rule, which costs an allocation and time proportional
+    /// to the message length times the number of rules. Lines wrapped with
+    /// `set_wrap_column` are not highlighted. Defaults to no rules.
+    #[must_use]
+    pub fn add_highlight(mut self, pattern: &str, style: Style) -> Self {
+        self.highlights.push((pattern.to_string(), style));
+        self
+    }
+
+    /// Register a pseudo-level: records logged with exactly `target`, e.g.
+    /// `info!(target: "audit", ...)`, are labelled `label` in `LogFormat::Human`
+    /// and styled with `style` instead of their level, as in
+    /// `[AUDIT][2021-08-22T15:49:01]User logged in`. The record keeps its
+    /// `log::Level` for filtering, sampling and the structured formats. A target
+    /// label replaces the level name set with `set_level_name` and is padded or
+    /// shortened with it; `FATAL` records keep their label. Registering the same
+    /// target again replaces its label. Defaults to no target labels.
+    #[must_use]
+    pub fn set_target_label(mut self, target: &str, label: &str, style: Style) -> Self {
+        self.target_labels
+            .retain(|existing| existing.target != target);
+        self.target_labels.push(TargetLabel {
+            target: target.to_string(),
+            label: label.to_string(),
+            style,
+        });
+        self
+    }
+
+    /// Set whether the level and timestamp are wrapped in brackets. Without
+    /// brackets the fields and the message are separated by a space. Defaults to `true`.
+    #[must_use]
+    pub fn set_brackets(mut self, brackets: bool) -> Self {
+        self.brackets = brackets;
+        self
+    }
+
+    /// Set whether human lines have the level field, for output already
+    /// filtered to one level or where the color tells the level. Without it a
+    /// line is `[2021-08-22T15:49:01]message`; target labels of
+    /// `set_target_label` and the `FATAL` label are left out too, while fatal
+    /// lines are still colored. JSON, CSV and the other formats keep the level.
+    /// Defaults to `true`.
+    #[must_use]
+    pub fn set_show_level(mut self, show: bool) -> Self {
+        self.show_level = show;
+        self
+    }
+
+    /// Set whether a repeated backtrace is collapsed: when the lines after the
+    /// first line of a multi-line message are identical to those of the
+    /// previous multi-line message, they are replaced by
+    /// `(backtrace identical to previous)`. Only consecutive occurrences are
+    /// compared, as the logger keeps just a hash of the last multi-line block;
+    /// single-line messages in between do not reset it. With it set, every
+    /// message is rendered to a string to look for a line break. Applies to
+    /// every format. Defaults to `false`.
+    #[must_use]
+    pub fn set_collapse_backtraces(mut self, collapse: bool) -> Self {
+        self.collapse_backtraces = collapse;
+        self
+    }
+
+    /// Set whether the main output writes the `detail` key-value. Records
+    /// carry the full detail of an event, such as a payload dump or a
+    /// backtrace, as `detail`, e.g.
+    /// `error!(detail = dump.as_str(); "Frame failed to decode")`, ready for a
+    /// console that shows the one-line summary and a file that keeps
+    /// everything: set this to `false` for the console and leave the file sink
+    /// of `add_sink` at its default, or the other way round with
+    /// `Sink::set_show_detail`. Only the `detail` key is affected, in every
+    /// format; the other key-values are written as usual. Defaults to `true`.
+    #[must_use]
+    pub fn set_show_detail(mut self, show: bool) -> Self {
+        self.show_detail = show;
+        self
+    }
+
+    /// Set whether the timestamp is written before the level. Defaults to `false`.
+    #[must_use]
+    pub fn set_timestamp_first(mut self, timestamp_first: bool) -> Self {
+        self.timestamp_first = timestamp_first;
+        self
+    }
+
+    /// Set whether the output is colored. Defaults to `ColorPreference::Auto`.
+    #[must_use]
+    pub fn set_color(mut self, color: ColorPreference) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the styles used for each field. Defaults to `ColorTheme::default()`.
+    #[must_use]
+    pub fn set_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Set the least severe level that is colored, e.g. `Level::Error` to
+    /// only color errors. Lines of less severe records are written plain, even
+    /// when `set_color` enables color. Defaults to `Level::Trace`, coloring
+    /// every level.
+    #[must_use]
+    pub fn set_color_min_level(mut self, level: Level) -> Self {
+        self.color_min_level = level;
+        self
+    }
+
+    /// Set a glyph written at the start of every `LogFormat::Human` line in
+    /// the color of its level, e.g. `Some('▎')` or `Some('│')`, for a log
+    /// viewer look:<br>
+    /// ▎\[INFO \]\[2021-08-22T15:49:01\]Decoder started<br><br>
+    /// The border follows the theme whatever `set_color_min_level` says, and
+    /// is left out together with the color: when the output is not colored,
+    /// such as under `NO_COLOR`, and while `LoggingGuard::set_color_enabled`
+    /// turns the color off. Defaults to `None`.
+    #[must_use]
+    pub fn set_level_border(mut self, border: Option<char>) -> Self {
+        self.level_border = border;
+        self
+    }
+
+    /// Set where the output is written. If a file can not be opened at init,
+    /// stderr is used instead and a warning is logged. Defaults to `LogTarget::Stderr`.
+    #[must_use]
+    pub fn set_target(mut self, target: LogTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set whether a new `LogTarget::File` starts with a UTF-8 byte order mark,
+    /// for Windows log viewers that otherwise guess a legacy encoding. The BOM
+    /// is written once, when logging is initialized and the file is empty; a
+    /// file that already has lines is appended to as is. `LogFormat::Binary`
+    /// files never get one. Most Unix tools do not expect a BOM, so leave it
+    /// off for files read there. Defaults to `false`.
+    #[must_use]
+    pub fn set_utf8_bom(mut self, bom: bool) -> Self {
+        self.utf8_bom = bom;
+        self
+    }
+
+    /// Set whether a line that can not be written to a `LogTarget::File`, e.g.
+    /// because the disk is full, is written to stderr instead. Failed writes
+    /// are counted either way and reported in the summary of
+    /// `set_summary_on_drop`; they never panic or reach the code that logged,
+    /// and every other output still receives the record. Write errors on
+    /// stderr and stdout are counted too, but have nowhere to fall back to.
+    /// Defaults to `false`, dropping the line.
+    #[must_use]
+    pub fn set_write_error_fallback(mut self, fallback: bool) -> Self {
+        self.write_error_fallback = fallback;
+        self
+    }
+
+    /// Route the records of `level` to `target` instead of the one set with
+    /// `set_target`, e.g. trace and debug to a file and the rest to stderr.
+    /// Call once per level to route. Each distinct target is a separate sink
+    /// with its own formatter: lines keep their order within a sink, but there
+    /// is no ordering between sinks, so a terminal showing both stdout and
+    /// stderr may interleave them out of order. A file that can not be opened
+    /// falls back to stderr as with `set_target`. Not used when logging to
+    /// logcat. Defaults to every level using the `set_target` target.
+    #[must_use]
+    pub fn set_level_target(mut self, level: Level, target: LogTarget) -> Self {
+        self.level_targets[level as usize - 1] = Some(target);
+        self
+    }
+
+    /// Also write every record to `sink`, in the sink's own format and color
+    /// policy, e.g. colored human lines on stderr and JSON in a file from the
+    /// same `init`. Call once per extra sink. The main output is set up by
+    /// this config as usual; each sink shares everything else with it (level,
+    /// filters, time and field options), so only the format, the color and
+    /// the target differ. Sinks are written after the main output, each with
+    /// its own formatter, so there is no ordering between them. A record has
+    /// the same sequence number in every sink and is counted once in the
+    /// stats and the summary; status lines are written to the main output
+    /// only. A file that can not be opened falls back to stderr as with
+    /// `set_target`. Not used for the android and browser console loggers.
+    /// Defaults to no sinks.
+    #[must_use]
+    pub fn add_sink(mut self, sink: Sink) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Set whether the number of key-values attached to each record is added as
+    /// a `fields=N` pair in human output and a `"fields":N` member in JSON, to
+    /// see why structured output is large. The global fields are not counted.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn set_field_count(mut self, count: bool) -> Self {
+        self.field_count = count;
+        self
+    }
+
+    /// Set the character column of the message at which the key-values of
+    /// human lines start, e.g. `Some(16)`, to read structured telemetry on a
+    /// console. Shorter messages are padded with spaces. Each `key=value` pair
+    /// but the last is padded to the widest pair of its key written so far, so
+    /// the pairs of consecutive records with the same keys line up:<br>
+    /// `[INFO ][2021-08-22T15:49:01]Position         icao=a1b2c3 alt=35000 rssi=-12.5`<br><br>
+    /// The message is rendered to a string to measure it. Only applies to
+    /// `LogFormat::Human`. Defaults to `None`, a single space before each pair.
+    #[must_use]
+    pub fn set_aligned_fields(mut self, column: Option<usize>) -> Self {
+        self.aligned_fields = column;
+        self
+    }
+
+    /// Set the keys of the key-values shown on human lines, in the order they
+    /// are shown, e.g. `&["icao", "alt"]` to follow a few fields of noisy
+    /// records. Record and global key-values with other keys are left out of
+    /// the line but still written by `LogFormat::Json` and `LogFormat::Csv`.
+    /// Only applies to `LogFormat::Human`. Defaults to no keys, which shows
+    /// every key-value in the order it was logged.
+    #[must_use]
+    pub fn set_shown_fields(mut self, keys: &[&str]) -> Self {
+        self.shown_fields = keys.iter().map(|key| key.to_string()).collect();
+        self
+    }
+
+    /// Set a `log::Log` implementation that is given every record as well, e.g.
+    /// to forward records to a system of your own while keeping this crate's
+    /// output. Records up to the configured level, per module with
+    /// `set_module_filter`, are passed first to this crate's output and then
+    /// to `logger`, if its `enabled` accepts them; the content filters and
+    /// sampling only apply to this crate's output. `log` has no way to report
+    /// errors, so failures must be handled inside `logger`, and a panic in it
+    /// reaches the code that logged. The logger is flushed along with the
+    /// output. Defaults to `None`.
+    #[must_use]
+    pub fn set_extra_logger(mut self, logger: Option<Arc<dyn log::Log>>) -> Self {
+        self.extra_logger = logger.map(ExtraLogger);
+        self
+    }
+
+    /// Set a closure that is run against the underlying `env_logger::Builder`,
+    /// for the options this crate does not expose. It runs after the crate has
+    /// applied its own format, level filter, color and target, and right
+    /// before the logger is built, so anything it sets overrides them; e.g.
+    /// replacing the format drops this crate's formatting entirely. Each
+    /// output of `set_level_target` has its own builder, so the closure is run
+    /// once per output. It is not used for the android and browser console
+    /// loggers, which do not use env_logger. Defaults to `None`.
+    #[must_use]
+    pub fn set_builder_hook(
+        mut self,
+        hook: impl Fn(&mut env_logger::Builder) + Send + Sync + 'static,
+    ) -> Self {
+        self.builder_hook = Some(BuilderHook(Arc::new(hook)));
+        self
+    }
+
+    /// Set the clock the timestamps of records are read from, in place of
+    /// `Utc::now`, e.g. a fixed time for golden output tests:<br>
+    /// `.set_clock(|| Utc.with_ymd_and_hms(2021, 8, 22, 22, 49, 1).unwrap())`<br><br>
+    /// Every timestamp of every format is taken from it; local timestamps are
+    /// its time in the local timezone, so tests comparing them should also fix
+    /// `TZ`. Elapsed times, uptime and time deltas are measured with
+    /// `std::time::Instant` and do not use it. Defaults to `None`, the system
+    /// clock.
+    #[must_use]
+    pub fn set_clock(mut self, clock: impl Fn() -> DateTime<Utc> + Send + Sync + 'static) -> Self {
+        self.clock = Some(Clock(Arc::new(clock)));
+        self
+    }
+
+    /// Set whether each line starts with a sequence number, to spot dropped or
+    /// reordered lines. The counter is shared by all threads of the process,
+    /// starts at 1 and resets when the process restarts. In `LogFormat::Json`
+    /// it is the `sequence` field and in `LogFormat::Csv` the first column.
+    /// `LogFormat::Columns`, `LogFormat::LegacyV1` and `LogFormat::Binary` do
+    /// not record it and take no numbers, so they leave no gaps in the other
+    /// outputs. Defaults to `false`.
+    #[must_use]
+    pub fn set_sequence_numbers(mut self, sequence_numbers: bool) -> Self {
+        self.sequence_numbers = sequence_numbers;
+        self
+    }
+
+    /// Set whether consecutive identical lines are collapsed. Lines are compared
+    /// by level, target, message and key-values, ignoring the timestamp. The first
+    /// line is written as usual and repeats are held back; when a different line
+    /// is logged, the last repeat is written with a ` (xN)` count of all
+    /// occurrences first. A run that is still going when the `LoggingGuard` is
+    /// dropped is written then, before the summary. Nothing is repainted, so
+    /// the output is safe for files and pipes. Only applies to
+    /// `LogFormat::Human`. Defaults to `false`.
+    #[must_use]
+    pub fn set_collapse_repeats(mut self, collapse: bool) -> Self {
+        self.collapse_repeats = collapse;
+        self
+    }
+
+    /// Set whether the `LoggingGuard` returned by `init` logs a summary line
+    /// when it is dropped, with the number of records logged per level and the
+    /// number of collapsed repeats. The summary is logged at info level. Defaults to `false`.
+    #[must_use]
+    pub fn set_summary_on_drop(mut self, summary: bool) -> Self {
+        self.summary_on_drop = summary;
+        self
+    }
+
+    /// Set how many of the most recent lines `init` keeps in memory for
+    /// `recent_logs`, e.g. to dump them on a keypress or a `--dump-logs` flag.
+    /// The lines are the ones the main output writes, including the targets
+    /// of `set_level_target`, with the styling removed; sinks of `add_sink`
+    /// and `LogFormat::Binary` frames are not kept. Each line costs its length
+    /// plus about 24 bytes, so 1000 human lines of 100 characters hold about
+    /// 125 kB, and every record written costs a copy of its line. Defaults to
+    /// `0`, which keeps none.
+    #[must_use]
+    pub fn set_recent_log_capacity(mut self, capacity: usize) -> Self {
+        self.recent_log_capacity = capacity;
+        self
+    }
+
+    /// Set whether human lines carry both the local and the UTC time, as in
+    /// `2021-08-22T15:49:01-07:00 / 2021-08-22T22:49:01Z`. Both use the time
+    /// format; the local time always gets its offset and the UTC time a `Z`, so
+    /// the timezone suffix is ignored. JSON output always has the UTC `timestamp`
+    /// and adds a `local_timestamp` field instead. Defaults to `false`.
+    #[must_use]
+    pub fn set_dual_timestamps(mut self, dual: bool) -> Self {
+        self.dual_timestamps = dual;
+        self
+    }
+
+    /// Set whether human lines show the time since the previous line, as in
+    /// `[INFO ][2021-08-22T15:49:01][+12ms]message`, to spot where time is spent.
+    /// The delta is to the last line written by any thread, so with several
+    /// threads logging it does not measure the time between the lines of one
+    /// thread. The first line shows `+0ms`. The field is not colored. Only
+    /// applies to `LogFormat::Human`. Defaults to `false`.
+    #[must_use]
+    pub fn set_time_deltas(mut self, deltas: bool) -> Self {
+        self.time_deltas = deltas;
+        self
+    }
+
+    /// Set whether records carry the time since logging was initialized next
+    /// to the timestamp, as in `[INFO ][2021-08-22T15:49:01][up 12.345s]message`
+    /// or `"uptime":12.345` in JSON, to correlate with devices that report
+    /// uptime and to order lines on hosts whose clock jumps at NTP sync. The
+    /// reference is the process, taken when `init` or `build` first runs with
+    /// this option, not the system boot time, which is not portably available.
+    /// It is monotonic and unaffected by changes of the wall clock. Only
+    /// applies to `LogFormat::Human` and `LogFormat::Json`. Defaults to `false`.
+    #[must_use]
+    pub fn set_uptime(mut self, uptime: bool) -> Self {
+        self.uptime = uptime;
+        self
+    }
+
+    /// Set whether human timestamps are the time since logging was initialized
+    /// as `MM:SS`, such as `01:02`, instead of the wall clock, for displays too
+    /// narrow for a full time and hosts without a reliable clock. The minutes
+    /// keep counting past `99`. The reference is the same as for `set_uptime`.
+    /// The time format, timezone suffix, dual and RFC3339 timestamps are
+    /// ignored. Defaults to `false`.
+    #[must_use]
+    pub fn set_elapsed_time(mut self, elapsed: bool) -> Self {
+        self.elapsed_time = elapsed;
+        self
+    }
+
+    /// Set human timestamps to the seconds since the Unix epoch, zero padded
+    /// to `width` digits so the column lines up for tools such as `awk`:
+    /// `Some(10)` gives `0987654321.123` and `1629672541.123`. The fractional
+    /// digits follow `set_time_precision` and `set_level_time_precision`, and
+    /// `TimePrecision::Seconds` leaves out the fraction. As the time is
+    /// timezone free, the time format, timezone suffix and dual and RFC3339
+    /// timestamps are ignored. Longer numbers are not cut, so a width of `10`
+    /// lines up until the year 2286, when the epoch gains an eleventh digit.
+    /// `set_elapsed_time` takes precedence. Defaults to `None`.
+    #[must_use]
+    pub fn set_epoch_timestamps(mut self, width: Option<usize>) -> Self {
+        self.epoch_width = width;
+        self
+    }
+
+    /// Set the number of fractional second digits in the RFC3339 timestamps of
+    /// `LogFormat::Json`, `LogFormat::Csv` and `set_rfc3339_timestamps`. Exactly
+    /// that many digits are written, padded with zeros. Other human timestamps
+    /// use the time format instead, e.g. `%H:%M:%S%.3f`. Defaults to
+    /// `TimePrecision::Millis`.
+    #[must_use]
+    pub fn set_time_precision(mut self, precision: TimePrecision) -> Self {
+        self.time_precision = precision;
+        self
+    }
+
+    /// Set the timestamp precision of records at `level`, replacing
+    /// `set_time_precision` for them, e.g. terse info lines with
+    /// `TimePrecision::Seconds` while errors and warnings get
+    /// `TimePrecision::Micros` to line them up with other logs. Applies where
+    /// `set_time_precision` does; fatal records use the precision of error.
+    /// Defaults to the precision of `set_time_precision` for every level.
+    #[must_use]
+    pub fn set_level_time_precision(mut self, level: Level, precision: TimePrecision) -> Self {
+        self.level_time_precisions[level as usize - 1] = Some(precision);
+        self
+    }
+
+    /// Set the column human lines are wrapped at, for fixed width displays.
+    /// The message and key-values are broken at the last space before the
+    /// column, or mid word if a word does not fit, and continue on the next
+    /// line indented to where the message starts; the prefix fields are only
+    /// on the first line. The indent is capped at half the column. Lines
+    /// already in the message are wrapped separately. Set the column at or
+    /// below the terminal width, as the terminal wraps anything longer again
+    /// without the indent. Only applies to `LogFormat::Human`. Defaults to `None`.
+    #[must_use]
+    pub fn set_wrap_column(mut self, column: Option<usize>) -> Self {
+        self.wrap_column = column;
+        self
+    }
+
+    /// Set the sampling rate of `level`: only 1 in `rate` records of the level
+    /// is written, starting with the first. Each level has its own counter, so
+    /// e.g. trace can be sampled while everything else is written in full. The
+    /// counters are atomic but not synchronized with the writes, so the sample
+    /// is approximate when several threads log at once. Records sampled out are
+    /// counted as such in the summaries rather than as logged. A `rate` of 0 is
+    /// treated as 1.
+    /// Defaults to `1` (no sampling) for every level.
+    #[must_use]
+    pub fn set_sample_rate(mut self, level: Level, rate: u64) -> Self {
+        self.sample_rates[level as usize - 1] = rate.max(1);
+        self
+    }
+
+    /// Set the least time between two written records of `level`, e.g.
+    /// `Some(Duration::from_secs(1))` for at most one warning a second,
+    /// whatever the warnings say. Records of the level logged within the
+    /// cooldown are dropped and counted as sampled out, and the next record
+    /// written has the count appended to its message: `gain clipped (12 suppressed)`.
+    /// The cooldown applies to the records left after sampling, so with both a
+    /// sample rate and a cooldown set only sampled records start and count
+    /// against it; collapsing repeats applies to the lines left after both.
+    /// Fatal records, banners and records of `log_forced` are always written.
+    /// Defaults to `None` for every level.
+    #[must_use]
+    pub fn set_level_cooldown(mut self, level: Level, cooldown: Option<Duration>) -> Self {
+        self.level_cooldowns[level as usize - 1] = cooldown;
+        self
+    }
+
+    /// Set whether a header line naming the fields in order, such as
+    /// `[level][timestamp]message`, is written once before the first record.
+    /// For `LogFormat::Human` the header follows the brackets, sequence number
+    /// and timestamp order settings; key-values vary per record and are not
+    /// named. For `LogFormat::Csv` and `LogFormat::Columns` it is the column
+    /// names. Never written for JSON, binary or common log output. Defaults
+    /// to `false`.
+    #[must_use]
+    pub fn set_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Mark the start and end of a run with banner lines, e.g.
+    /// `"=== logging started ==="` and `"=== logging stopped ==="`. `start` is
+    /// written by `init` and `stop` when the returned guard is dropped, after
+    /// the summary of `set_summary_on_drop` and before the output is flushed.
+    /// In `LogFormat::Human` and `LogFormat::CommonLog` a banner is the text
+    /// alone, in the timestamp style without the other fields; the other
+    /// formats write it as an info record, so readers of JSON or CSV see no
+    /// new kind of row. Banners are info records as far as filtering goes, so
+    /// they are not shown with a level below info. Defaults to no banners.
+    #[must_use]
+    pub fn set_banners(mut self, start: &str, stop: &str) -> Self {
+        self.banners = Some((start.to_string(), stop.to_string()));
+        self
+    }
+
+    /// Set substrings that drop a record when its message contains any of them,
+    /// e.g. `&["heartbeat ok"]` to hide known benign noise. Level and target
+    /// filters only see the metadata, so with any substrings set every enabled
+    /// record has its message rendered to a string before it is formatted,
+    /// which costs an allocation per record. Dropped records are counted as
+    /// filtered in the summaries rather than as logged. Defaults to no substrings.
+    #[must_use]
+    pub fn set_deny_messages(mut self, substrings: &[&str]) -> Self {
+        self.deny_messages = substrings.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Set the minimum length of a message in bytes; shorter messages are
+    /// dropped like those matching `set_deny_messages`, with the same cost of
+    /// rendering the message first. Defaults to `0`.
+    #[must_use]
+    pub fn set_min_message_length(mut self, length: usize) -> Self {
+        self.min_message_length = length;
+        self
+    }
+
+    /// Set a slow log threshold: records with a `duration_ms` key-value below
+    /// it are dropped, so only slow operations are written, e.g.
+    /// `log::info!(duration_ms = elapsed.as_millis(); "decoded frame")`. The
+    /// value may be an integer, a float or a string holding a number of
+    /// milliseconds. Records without the field pass, unless
+    /// `set_slow_log_keep_untimed` says otherwise. Dropped records are counted
+    /// as filtered. Defaults to `None`.
+    #[must_use]
+    pub fn set_slow_log_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_log_threshold = threshold;
+        self
+    }
+
+    /// Set whether records without a `duration_ms` key-value, or with one that
+    /// is not a number, are kept while a slow log threshold is set. Defaults
+    /// to `true`.
+    #[must_use]
+    pub fn set_slow_log_keep_untimed(mut self, keep: bool) -> Self {
+        self.slow_log_keep_untimed = keep;
+        self
+    }
+
+    /// Set what is written for records whose message is empty, in every
+    /// format. A record is empty when its message is empty or only
+    /// whitespace, before truncation; key-values do not count. Defaults to
+    /// `EmptyMessage::Keep`, which writes a whitespace message as it is.
+    #[must_use]
+    pub fn set_empty_message(mut self, empty: EmptyMessage) -> Self {
+        self.empty_message = empty;
+        self
+    }
+
+    /// Set how often a summary of the records that were not written is logged,
+    /// e.g. `Some(Duration::from_secs(60))`. The summary is an info record from
+    /// the `sdre_rust_logging` target, such as `Not written in the last 60s: 120
+    /// sampled out, 4 filtered, 9 collapsed repeats`, with the counts since the
+    /// previous summary. It is written ahead of the first record after the
+    /// interval has passed, so nothing is logged while the application is
+    /// quiet, and it is skipped if nothing was dropped. The summary is not
+    /// subject to the filters or sampling. Defaults to `None`.
+    #[must_use]
+    pub fn set_drop_summary_interval(mut self, interval: Option<Duration>) -> Self {
+        self.drop_summary_interval = interval;
+        self
+    }
+
+    /// Set how often the current logging rate is logged, e.g.
+    /// `Some(Duration::from_secs(30))`, to spot log storms on a live feed. The
+    /// status is an info record from the `sdre_rust_logging` target, such as
+    /// `Logging 12.5 records/s over the last 10s`, computed from the records
+    /// written within `set_rate_status_window`. Like the drop summary it is
+    /// written ahead of the first record after the interval has passed, and it
+    /// is not counted in the rate itself. Defaults to `None`.
+    #[must_use]
+    pub fn set_rate_status_interval(mut self, interval: Option<Duration>) -> Self {
+        self.rate_status_interval = interval;
+        self
+    }
+
+    /// Set the sliding window the rate of `set_rate_status_interval` is
+    /// computed over. Emit times are kept with millisecond resolution, so the
+    /// memory used is bounded by the window length rather than by the rate.
+    /// Defaults to `10s`.
+    #[must_use]
+    pub fn set_rate_status_window(mut self, window: Duration) -> Self {
+        self.rate_status_window = window;
+        self
+    }
+
+    /// Set how often the time spent writing records to the output is logged,
+    /// e.g. `Some(Duration::from_secs(60))`, to notice a slow disk or a full
+    /// pipe before logging holds up the application. The status is an info
+    /// record from the `sdre_rust_logging` target, such as
+    /// `Write latency p50 12.1µs, p99 310.4µs, max 2.3ms over 1520 writes in the last 60s`,
+    /// over the writes since the previous status, timed from the first byte
+    /// of a record to its flush. It is written ahead of the first record after
+    /// the interval has passed, like the rate status. At most 100 000 writes
+    /// per interval are timed. Each sink of `add_sink` reports its own
+    /// latency; logcat and the browser console are not timed. When `None`,
+    /// the outputs are written without any timing. Defaults to `None`.
+    #[must_use]
+    pub fn set_write_latency_interval(mut self, interval: Option<Duration>) -> Self {
+        self.write_latency_interval = interval;
+        self
+    }
+
+    /// Set how long the logger may be silent before it logs a heartbeat, e.g.
+    /// `Some(Duration::from_secs(300))`, so an idle receiver can be told from
+    /// a hung one. `init` starts a thread that logs
+    /// `Heartbeat: no records in the last 300s` from the `sdre_rust_logging`
+    /// target, at the level of `set_heartbeat_level`, whenever no record was
+    /// written for the interval; every written record, including the
+    /// heartbeat, starts the interval over. The heartbeat stops when the
+    /// `LoggingGuard` is dropped. `build` does not start the thread.
+    /// Defaults to `None`.
+    #[must_use]
+    pub fn set_heartbeat_interval(mut self, interval: Option<Duration>) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Set the level of the heartbeat of `set_heartbeat_interval`. It is
+    /// filtered like any other record, so a level above `set_level` silences
+    /// it. Defaults to `Level::Info`.
+    #[must_use]
+    pub fn set_heartbeat_level(mut self, level: Level) -> Self {
+        self.heartbeat_level = level;
+        self
+    }
+
+    /// Set how many records `LoggingGuard::pause` holds for the console.
+    /// Once that many are held, each further record drops the oldest one,
+    /// and a warning written on resume counts the records dropped. Defaults
+    /// to `1000`.
+    #[must_use]
+    pub fn set_pause_capacity(mut self, capacity: usize) -> Self {
+        self.pause_capacity = capacity;
+        self
+    }
+
+    /// Set whether `LoggingGuard::resume` writes the records held while the
+    /// console was paused or throws them away. Defaults to `ResumePolicy::Flush`.
+    #[must_use]
+    pub fn set_resume_policy(mut self, policy: ResumePolicy) -> Self {
+        self.resume_policy = policy;
+        self
+    }
+
+    /// Set the tag records are logged under in logcat. Only used on Android
+    /// with the `android` feature. `None` writes to stderr as on other
+    /// platforms, which is usually what Termux users want. Defaults to `Some("sdre-rust-logging")`.
+    #[cfg(feature = "android")]
+    #[must_use]
+    pub fn set_android_tag(mut self, tag: Option<&str>) -> Self {
+        self.android_tag = tag.map(str::to_string);
+        self
+    }
+
+    /// Set whether records go to the browser console. Only used on wasm32 with
+    /// the `wasm` feature. Each record is sent as its uncolored human line with
+    /// the console method matching its level: `console.error`, `console.warn`,
+    /// `console.info`, and `console.debug` for debug and trace. `false` writes
+    /// to stderr as on other platforms, for WASI runtimes. `set_time_deltas`,
+    /// `set_drop_summary_interval`, `set_rate_status_interval`,
+    /// `set_write_latency_interval`, `set_heartbeat_interval`,
+    /// `set_level_cooldown`, `set_uptime` and `set_elapsed_time` need
+    /// `std::time::Instant`, which panics on `wasm32-unknown-unknown`, so
+    /// leave them off there. Defaults to `true`.
+    #[cfg(feature = "wasm")]
+    #[must_use]
+    pub fn set_browser_console(mut self, console: bool) -> Self {
+        self.browser_console = console;
+        self
+    }
+
+    #[must_use]
+    pub fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    #[must_use]
+    pub fn timezone_suffix(&self) -> TimezoneSuffix {
+        self.timezone_suffix
+    }
+
+    #[must_use]
+    pub fn max_message_length(&self) -> Option<usize> {
+        self.max_message_length
+    }
+
+    #[must_use]
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    #[must_use]
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    #[must_use]
+    pub fn escape_control_characters(&self) -> bool {
+        self.escape_control_characters
+    }
+
+    #[must_use]
+    pub fn strip_message_ansi(&self) -> bool {
+        self.strip_message_ansi
+    }
+
+    #[must_use]
+    pub fn time_format(&self) -> &str {
+        &self.time_format
+    }
+
+    #[must_use]
+    pub fn rfc3339_timestamps(&self) -> bool {
+        self.rfc3339_timestamps
+    }
+
+    #[must_use]
+    pub fn utc_timestamps(&self) -> bool {
+        self.utc_timestamps
+    }
+
+    #[must_use]
+    pub fn uptime(&self) -> bool {
+        self.uptime
+    }
+
+    #[must_use]
+    pub fn date_time_separator(&self) -> char {
+        self.date_time_separator
+    }
+
+    #[must_use]
+    pub fn level_label(&self) -> LevelLabel {
+        self.level_label
+    }
+
+    #[must_use]
+    pub fn level_case(&self) -> Option<LevelCase> {
+        self.level_case
+    }
+
+    #[must_use]
+    pub fn level_name(&self, level: Level) -> &str {
+        &self.level_names[level as usize - 1]
+    }
+
+    /// The label and style registered for `target` with `set_target_label`
+    #[must_use]
+    pub fn target_label(&self, target: &str) -> Option<(&str, Style)> {
+        self.target_labels
+            .iter()
+            .find(|existing| existing.target == target)
+            .map(|existing| (existing.label.as_str(), existing.style))
+    }
+
+    /// The labels registered with `set_target_label`
+    pub(crate) fn target_label_names(&self) -> impl Iterator<Item = &str> {
+        self.target_labels.iter().map(|label| label.label.as_str())
+    }
+
+    #[must_use]
+    pub fn brackets(&self) -> bool {
+        self.brackets
+    }
+
+    #[must_use]
+    pub fn timestamp_first(&self) -> bool {
+        self.timestamp_first
+    }
+
+    #[must_use]
+    pub fn color(&self) -> ColorPreference {
+        self.color
+    }
+
+    #[must_use]
+    pub fn theme(&self) -> &ColorTheme {
+        &self.theme
+    }
+
+    #[must_use]
+    pub fn color_min_level(&self) -> Level {
+        self.color_min_level
+    }
+
+    #[must_use]
+    pub fn level_border(&self) -> Option<char> {
+        self.level_border
+    }
+
+    #[must_use]
+    pub fn target(&self) -> &LogTarget {
+        &self.target
+    }
+
+    #[must_use]
+    pub fn json_pretty(&self) -> bool {
+        self.json_pretty
+    }
+
+    #[must_use]
+    pub fn column_widths(&self) -> ColumnWidths {
+        self.column_widths
+    }
+
+    #[must_use]
+    pub fn json_timestamp(&self) -> bool {
+        self.json_timestamp
+    }
+
+    #[must_use]
+    pub fn drop_summary_interval(&self) -> Option<Duration> {
+        self.drop_summary_interval
+    }
+
+    #[must_use]
+    pub fn rate_status_interval(&self) -> Option<Duration> {
+        self.rate_status_interval
+    }
+
+    #[must_use]
+    pub fn rate_status_window(&self) -> Duration {
+        self.rate_status_window
+    }
+
+    #[must_use]
+    pub fn write_latency_interval(&self) -> Option<Duration> {
+        self.write_latency_interval
+    }
+
+    #[must_use]
+    pub fn heartbeat_interval(&self) -> Option<Duration> {
+        self.heartbeat_interval
+    }
+
+    #[must_use]
+    pub fn heartbeat_level(&self) -> Level {
+        self.heartbeat_level
+    }
+
+    #[must_use]
+    pub fn pause_capacity(&self) -> usize {
+        self.pause_capacity
+    }
+
+    #[must_use]
+    pub fn resume_policy(&self) -> ResumePolicy {
+        self.resume_policy
+    }
+
+    #[must_use]
+    pub fn field_count(&self) -> bool {
+        self.field_count
+    }
+
+    #[must_use]
+    pub fn aligned_fields(&self) -> Option<usize> {
+        self.aligned_fields
+    }
+
+    #[must_use]
+    pub fn shown_fields(&self) -> &[String] {
+        &self.shown_fields
+    }
+
+    #[must_use]
+    pub fn extra_logger(&self) -> Option<Arc<dyn log::Log>> {
+        self.extra_logger.as_ref().map(|extra| extra.0.clone())
+    }
+
+    /// Run the closure from `set_builder_hook`, if set, against `builder`
+    pub(crate) fn run_builder_hook(&self, builder: &mut env_logger::Builder) {
+        if let Some(hook) = &self.builder_hook {
+            (hook.0)(builder);
+        }
+    }
+
+    #[must_use]
+    pub fn write_error_fallback(&self) -> bool {
+        self.write_error_fallback
+    }
+
+    #[must_use]
+    pub fn module_filter(&self) -> &ModuleFilter {
+        &self.module_filter
+    }
+
+    #[must_use]
+    pub fn highlights(&self) -> &[(String, Style)] {
+        &self.highlights
+    }
+
+    #[must_use]
+    pub fn show_detail(&self) -> bool {
+        self.show_detail
+    }
+
+    #[must_use]
+    pub fn collapse_backtraces(&self) -> bool {
+        self.collapse_backtraces
+    }
+
+    #[must_use]
+    pub fn show_level(&self) -> bool {
+        self.show_level
+    }
+
+    #[must_use]
+    pub fn elapsed_time(&self) -> bool {
+        self.elapsed_time
+    }
+
+    #[must_use]
+    pub fn recent_log_capacity(&self) -> usize {
+        self.recent_log_capacity
+    }
+
+    #[must_use]
+    pub fn epoch_timestamps(&self) -> Option<usize> {
+        self.epoch_width
+    }
+
+    #[must_use]
+    pub fn utf8_bom(&self) -> bool {
+        self.utf8_bom
+    }
+
+    #[must_use]
+    pub fn init_level(&self) -> Option<LevelFilter> {
+        self.init_level
+    }
+
+    /// The theme in effect, as changed by `LoggingGuard::set_theme`
+    pub(crate) fn current_theme(&self) -> ColorTheme {
+        self.live_theme
+            .as_ref()
+            .map_or(self.theme, |live| live.theme())
+    }
+
+    /// Whether `LoggingGuard::set_color_enabled` left the color on
+    pub(crate) fn color_enabled(&self) -> bool {
+        self.live_theme.as_ref().map_or(true, |live| live.color())
+    }
+
+    /// The current time from the clock of `set_clock`
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.clock
+            .as_ref()
+            .map_or_else(Utc::now, |clock| (clock.0)())
+    }
+
+    /// The lines of `recent_logs`, if this is the config of the output `init`
+    /// installed
+    pub(crate) fn recent_lines(&self) -> Option<Arc<RecentLines>> {
+        self.recent_lines.clone()
+    }
+
+    /// This config without the lines of `recent_logs`, for the sinks of
+    /// `add_sink`, whose lines would repeat those of the output
+    pub(crate) fn without_recent_lines(mut self) -> Self {
+        self.recent_lines = None;
+        self
+    }
+
+    /// Run `f` with the module filter in effect: the one set through
+    /// `LoggingGuard::set_module_filter` once `init` ran, otherwise the
+    /// configured one
+    pub(crate) fn with_module_filter<R>(&self, f: impl FnOnce(&ModuleFilter) -> R) -> R {
+        match &self.live_filter {
+            Some(live) => live.read(f),
+            None => f(&self.module_filter),
+        }
+    }
+
+    /// The level of targets without a module directive after the init phase
+    fn steady_level(&self, filter: &ModuleFilter) -> LevelFilter {
+        filter.default_level().unwrap_or(self.level)
+    }
+
+    /// The init level while the init phase of `set_init_level` lasts
+    fn phase_init_level(&self) -> Option<LevelFilter> {
+        self.init_level.filter(|_| !crate::init_finished())
+    }
+
+    /// The level the outputs let records of `target` through at, with the
+    /// more verbose one of both phases for targets without a module
+    /// directive. Records beyond the level of the current phase are dropped
+    /// by `phase_level_for`.
+    pub(crate) fn gate_level_for(&self, target: &str) -> LevelFilter {
+        self.with_module_filter(|filter| {
+            filter.module_level(target).unwrap_or_else(|| {
+                let steady = self.steady_level(filter);
+                self.init_level.map_or(steady, |init| init.max(steady))
+            })
+        })
+    }
+
+    /// The level of `target` in the current phase
+    pub(crate) fn phase_level_for(&self, target: &str) -> LevelFilter {
+        self.with_module_filter(|filter| {
+            filter.module_level(target).unwrap_or_else(|| {
+                self.phase_init_level()
+                    .unwrap_or_else(|| self.steady_level(filter))
+            })
+        })
+    }
+
+    /// The most verbose level of any target in the current phase, for
+    /// `log::set_max_level`
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.with_module_filter(|filter| match self.phase_init_level() {
+            Some(init) => filter
+                .modules()
+                .iter()
+                .map(|(_, level)| *level)
+                .fold(init, Ord::max),
+            None => filter.max_level(self.level),
+        })
+    }
+
+    #[must_use]
+    pub fn sinks(&self) -> &[Sink] {
+        &self.sinks
+    }
+
+    /// Where the records of `level` are written, see `set_level_target`
+    #[must_use]
+    pub fn level_target(&self, level: Level) -> &LogTarget {
+        self.level_targets[level as usize - 1]
+            .as_ref()
+            .unwrap_or(&self.target)
+    }
+
+    #[must_use]
+    pub fn expected_utc_offset(&self) -> Option<FixedOffset> {
+        self.expected_utc_offset
+    }
+
+    #[must_use]
+    pub fn utc_offset_tolerance(&self) -> Duration {
+        self.utc_offset_tolerance
+    }
+
+    #[must_use]
+    pub fn sequence_numbers(&self) -> bool {
+        self.sequence_numbers
+    }
+
+    #[must_use]
+    pub fn collapse_repeats(&self) -> bool {
+        self.collapse_repeats
+    }
+
+    #[must_use]
+    pub fn summary_on_drop(&self) -> bool {
+        self.summary_on_drop
+    }
+
+    #[must_use]
+    pub fn dual_timestamps(&self) -> bool {
+        self.dual_timestamps
+    }
+
+    #[must_use]
+    pub fn time_deltas(&self) -> bool {
+        self.time_deltas
+    }
+
+    #[must_use]
+    pub fn time_precision(&self) -> TimePrecision {
+        self.time_precision
+    }
+
+    /// The precision of timestamps at `level`, see `set_level_time_precision`
+    #[must_use]
+    pub fn level_time_precision(&self, level: Level) -> TimePrecision {
+        self.level_time_precisions[level as usize - 1].unwrap_or(self.time_precision)
+    }
+
+    #[must_use]
+    pub fn wrap_column(&self) -> Option<usize> {
+        self.wrap_column
+    }
+
+    #[must_use]
+    pub fn sample_rate(&self, level: Level) -> u64 {
+        self.sample_rates[level as usize - 1]
+    }
+
+    #[must_use]
+    pub fn level_cooldown(&self, level: Level) -> Option<Duration> {
+        self.level_cooldowns[level as usize - 1]
+    }
+
+    #[must_use]
+    pub fn header(&self) -> bool {
+        self.header
+    }
+
+    #[must_use]
+    pub fn banners(&self) -> Option<(&str, &str)> {
+        self.banners
+            .as_ref()
+            .map(|(start, stop)| (start.as_str(), stop.as_str()))
+    }
+
+    #[must_use]
+    pub fn deny_messages(&self) -> &[String] {
+        &self.deny_messages
+    }
+
+    #[must_use]
+    pub fn min_message_length(&self) -> usize {
+        self.min_message_length
+    }
+
+    #[must_use]
+    pub fn slow_log_threshold(&self) -> Option<Duration> {
+        self.slow_log_threshold
+    }
+
+    #[must_use]
+    pub fn slow_log_keep_untimed(&self) -> bool {
+        self.slow_log_keep_untimed
+    }
+
+    #[must_use]
+    pub fn empty_message(&self) -> EmptyMessage {
+        self.empty_message
+    }
+
+    #[cfg(feature = "android")]
+    #[must_use]
+    pub fn android_tag(&self) -> Option<&str> {
+        self.android_tag.as_deref()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[must_use]
+    pub fn browser_console(&self) -> bool {
+        self.browser_console
+    }
+
+    /// Initialize logging with this configuration<br><br>
+    /// Hold the returned guard until the application exits, usually as
+    /// `let _guard = config.init();` in `main`; dropping it flushes the output.
+    #[must_use = "dropping the guard ends logging setup; bind it with let _guard"]
+    pub fn init(mut self) -> LoggingGuard {
+        let live_theme = Arc::new(LiveTheme::new(self.theme));
+        self.live_theme = Some(Arc::clone(&live_theme));
+        let live_filter = Arc::new(LiveFilter::new(self.module_filter.clone()));
+        self.live_filter = Some(Arc::clone(&live_filter));
+        let recent = (self.recent_log_capacity > 0)
+            .then(|| Arc::new(RecentLines::new(self.recent_log_capacity)));
+        self.recent_lines = recent.clone();
+        let banners = self.banners.clone();
+        let offset_warning = self.expected_utc_offset.and_then(|expected| {
+            let local = self.now().with_timezone(&Local).offset().fix();
+            crate::utc_offset_warning(local, expected, self.utc_offset_tolerance)
+        });
+        let heartbeat = self
+            .heartbeat_interval
+            .map(|interval| (interval, self.heartbeat_level));
+        let pause = (self.pause_capacity, self.resume_policy);
+        let summary = self.summary_on_drop;
+        let installed = crate::set_builder(self);
+        if installed {
+            crate::writer::PAUSE.configure(pause.0, pause.1);
+            if let Some(recent) = recent {
+                crate::recent::publish(recent);
+            }
+            if let Some((start, _)) = &banners {
+                crate::log_banner(start);
+            }
+            if let Some(warning) = offset_warning {
+                log::warn!(target: "sdre_rust_logging", "{warning}");
+            }
+            if let Some((interval, level)) = heartbeat {
+                crate::heartbeat::start(interval, level);
+            }
+        }
+        LoggingGuard {
+            installed,
+            summary,
+            stop_banner: banners.map(|(_, stop)| stop),
+            live_theme,
+            live_filter,
+        }
+    }
+
+    /// Build the logger for this configuration without installing it, for
+    /// embedders that manage the `log` facade themselves, e.g. to combine
+    /// several loggers or to log to one in a test.<br><br>
+    /// The caller installs it, if at all, and sets the maximum level:<br>
+    /// `log::set_boxed_logger(config.clone().build())?; log::set_max_level(config.level());`<br><br>
+    /// Unlike `init`, nothing is global: `current_config`, the start banner,
+    /// `capture_early_logs`, `recent_logs` and the `LoggingGuard` summary do not
+    /// apply. A log file that can not be opened is reported by a warning
+    /// written to the returned logger.
+    #[must_use]
+    pub fn build(self) -> Box<dyn log::Log> {
+        crate::build_standalone(&self)
+    }
+}
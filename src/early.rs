@@ -0,0 +1,304 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Capture of the records logged before logging is initialized
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Only set once the early logger is the global logger
+static EARLY_LOGGER: OnceLock<&'static EarlyLogger> = OnceLock::new();
+
+/// A record held until the real logger is installed. Key-values are not kept.
+struct EarlyRecord {
+    level: Level,
+    target: String,
+    message: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+}
+
+/// Logger that buffers records until the real logger is attached, then
+/// forwards everything to it
+pub(crate) struct EarlyLogger {
+    capacity: usize,
+    /// Whether records before init are reported as bugs, see `capture_early_logs_strict`
+    strict: bool,
+    /// Where the first record before init was logged, in strict mode
+    first_early: Mutex<Option<String>>,
+    buffer: Mutex<VecDeque<EarlyRecord>>,
+    dropped: AtomicU64,
+    inner: OnceLock<Box<dyn Log>>,
+}
+
+impl EarlyLogger {
+    fn new(capacity: usize, strict: bool) -> Self {
+        Self {
+            capacity,
+            strict,
+            first_early: Mutex::new(None),
+            buffer: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+            inner: OnceLock::new(),
+        }
+    }
+
+    /// Report a record logged before init in strict mode: a panic in debug
+    /// builds, otherwise its location is kept for the warning at init
+    fn report_early(&self, record: &Record) {
+        let location = format!(
+            "{} at {}:{}",
+            record.target(),
+            record.file().unwrap_or("<unknown>"),
+            record.line().unwrap_or(0)
+        );
+        debug_assert!(
+            false,
+            "record logged before logging was initialized: {location}"
+        );
+        let mut first_early = self
+            .first_early
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        first_early.get_or_insert(location);
+    }
+
+    /// Where the first record before init was logged, in strict mode
+    fn first_early(&self) -> Option<String> {
+        self.first_early
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replay the buffered records through `logger` and forward all later
+    /// records to it. Returns the number of records dropped because the
+    /// buffer was full, or `None` if a logger was already attached.
+    fn attach(&self, logger: Box<dyn Log>) -> Option<u64> {
+        let mut buffer = self
+            .buffer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.inner.get().is_some() {
+            return None;
+        }
+
+        // the buffer stays locked until the logger is set, so records logged
+        // in the meantime wait and are written after the replayed ones
+        for early in buffer.drain(..) {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("{}", early.message))
+                    .level(early.level)
+                    .target(&early.target)
+                    .module_path(early.module_path.as_deref())
+                    .file(early.file.as_deref())
+                    .line(early.line)
+                    .build(),
+            );
+        }
+        let _ = self.inner.set(logger);
+        Some(self.dropped.load(Ordering::Relaxed))
+    }
+}
+
+impl Log for EarlyLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match self.inner.get() {
+            Some(inner) => inner.enabled(metadata),
+            None => self.capacity > 0,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(inner) = self.inner.get() {
+            return inner.log(record);
+        }
+
+        let mut buffer = self
+            .buffer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(inner) = self.inner.get() {
+            drop(buffer);
+            return inner.log(record);
+        }
+        if self.strict {
+            self.report_early(record);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        if buffer.len() == self.capacity {
+            buffer.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        buffer.push_back(EarlyRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            module_path: record.module_path().map(str::to_string),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+        });
+    }
+
+    fn flush(&self) {
+        if let Some(inner) = self.inner.get() {
+            inner.flush();
+        }
+    }
+}
+
+/// Start capturing records before logging is initialized<br><br>
+/// Installs a lightweight logger that keeps the last `capacity` records of any
+/// level in memory. When `enable_logging` or `LoggingConfig::init` runs, the
+/// captured records are written through the real formatter, filtered by its
+/// level, followed by a warning if older records were dropped because the
+/// buffer was full. Records keep their level, target and message but not their
+/// key-values, and are timestamped when they are replayed.<br><br>
+/// If logging is never initialized the captured records are never written,
+/// and at most `capacity` of them stay in memory. Call this first thing in
+/// `main`; returns `false` if a logger is already installed.
+pub fn capture_early_logs(capacity: usize) -> bool {
+    install_early(capacity, false)
+}
+
+/// Start capturing records before logging is initialized, treating any such
+/// record as a bug<br><br>
+/// A development aid for finding code that logs before `enable_logging` or
+/// `LoggingConfig::init` runs, which `log` otherwise silently drops. In debug
+/// builds the first such record panics, naming its target and location. In
+/// release builds the records are captured as with `capture_early_logs` and a
+/// warning naming the first one is logged at init. Not meant to be left on in
+/// production; returns `false` if a logger is already installed.
+pub fn capture_early_logs_strict(capacity: usize) -> bool {
+    install_early(capacity, true)
+}
+
+fn install_early(capacity: usize, strict: bool) -> bool {
+    if EARLY_LOGGER.get().is_some() {
+        return false;
+    }
+    // `log` keeps a `&'static` logger; if another logger won, the few bytes
+    // of this one are leaked
+    let logger: &'static EarlyLogger = Box::leak(Box::new(EarlyLogger::new(capacity, strict)));
+    if log::set_logger(logger).is_err() {
+        return false;
+    }
+    let _ = EARLY_LOGGER.set(logger);
+    log::set_max_level(LevelFilter::Trace);
+    true
+}
+
+/// Install `logger` as the global logger, behind the early capture logger if
+/// `capture_early_logs` was called. Returns whether it was installed.
+pub(crate) fn install(logger: Box<dyn Log>, level: LevelFilter) -> bool {
+    match EARLY_LOGGER.get() {
+        Some(early) => {
+            let Some(dropped) = early.attach(logger) else {
+                return false;
+            };
+            log::set_max_level(level);
+            if let Some(location) = early.first_early() {
+                log::warn!(
+                    "Records were logged before logging was initialized, first by {location}"
+                );
+            }
+            if dropped > 0 {
+                log::warn!("{dropped} records logged before logging was initialized were dropped");
+            }
+            true
+        }
+        None => {
+            if log::set_boxed_logger(logger).is_err() {
+                return false;
+            }
+            log::set_max_level(level);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Collect {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Info
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                self.0.lock().unwrap().push(format!("{}", record.args()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn log(logger: &EarlyLogger, level: Level, message: &str) {
+        logger.log(
+            &Record::builder()
+                .args(format_args!("{message}"))
+                .level(level)
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_early_capture() {
+        let early = EarlyLogger::new(2, false);
+        log(&early, Level::Info, "dropped");
+        log(&early, Level::Debug, "filtered");
+        log(&early, Level::Warn, "kept");
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        assert_eq!(early.attach(Box::new(Collect(lines.clone()))), Some(1));
+        log(&early, Level::Info, "after");
+        assert_eq!(*lines.lock().unwrap(), ["kept", "after"]);
+        assert_eq!(early.attach(Box::new(Collect(lines.clone()))), None);
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "before logging was initialized")
+    )]
+    fn test_strict_early_capture() {
+        let early = EarlyLogger::new(2, true);
+        early.log(
+            &Record::builder()
+                .args(format_args!("too soon"))
+                .level(Level::Info)
+                .target("config")
+                .file(Some("src/main.rs"))
+                .line(Some(12))
+                .build(),
+        );
+        assert_eq!(
+            early.first_early().as_deref(),
+            Some("config at src/main.rs:12")
+        );
+    }
+}
@@ -0,0 +1,533 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Rendering of the key-values (structured fields) attached to records
+
+use crate::format::write_json_string;
+use log::kv::{self, Key, Source, Value, VisitSource, VisitValue};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+
+static GLOBAL_FIELDS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Set key-values added to every record, such as the deployment, region or
+/// instance id<br><br>
+/// The fields follow the record's own key-values in human, JSON and CSV
+/// output. A record key-value with the same key takes precedence and the
+/// global field is left out of that record. The fields can be set once per
+/// process, before or after logging is initialized, and are read without
+/// locking. Returns `false` if they were already set.<br><br>
+/// `set_global_fields(&[("region", "eu-west"), ("instance", "feeder-3")]);`
+pub fn set_global_fields(fields: &[(&str, &str)]) -> bool {
+    GLOBAL_FIELDS
+        .set(
+            fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        )
+        .is_ok()
+}
+
+fn global_fields() -> &'static [(String, String)] {
+    GLOBAL_FIELDS.get().map_or(&[], Vec::as_slice)
+}
+
+/// The version, commit and build time of the application, for correlating
+/// log lines with releases<br><br>
+/// The values are usually compiled in: `CARGO_PKG_VERSION` is set by cargo,
+/// and a build script can add the others with `cargo:rustc-env`, e.g.
+/// `println!("cargo:rustc-env=GIT_COMMIT={commit}")` with the output of
+/// `git rev-parse --short HEAD`. Then<br>
+/// `BuildInfo::new(env!("CARGO_PKG_VERSION"), option_env!("GIT_COMMIT").unwrap_or(""), option_env!("BUILD_TIME").unwrap_or(""))`<br><br>
+/// gives the fields `version`, `commit` and `build_time`; empty values are
+/// left out. Add them to every record with `set_global`, or log them once at
+/// startup with `log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    version: String,
+    commit: String,
+    build_time: String,
+}
+
+impl BuildInfo {
+    #[must_use]
+    pub fn new(version: &str, commit: &str, build_time: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            commit: commit.to_string(),
+            build_time: build_time.to_string(),
+        }
+    }
+
+    /// The non-empty values with their keys
+    #[must_use]
+    pub fn fields(&self) -> Vec<(&str, &str)> {
+        [
+            ("version", self.version.as_str()),
+            ("commit", self.commit.as_str()),
+            ("build_time", self.build_time.as_str()),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .collect()
+    }
+
+    /// Add the fields to every record with `set_global_fields`, which can
+    /// only be called once; pass other global fields along with
+    /// `set_global_fields(&[info.fields(), other].concat())` instead. Returns
+    /// `false` if the global fields were already set.
+    pub fn set_global(&self) -> bool {
+        set_global_fields(&self.fields())
+    }
+
+    /// Log the fields as an info record from the `sdre_rust_logging` target,
+    /// `Build info version=1.2.0 commit=1a2b3c4 build_time=2024-05-01T12:00:00Z`,
+    /// e.g. right after logging is initialized
+    pub fn log(&self) {
+        let fields = self.fields();
+        log::logger().log(
+            &log::Record::builder()
+                .args(format_args!("Build info"))
+                .level(log::Level::Info)
+                .target("sdre_rust_logging")
+                .key_values(&fields)
+                .build(),
+        );
+    }
+}
+
+/// The key of the detail a console can leave out, see `LoggingConfig::set_show_detail`
+pub(crate) const DETAIL_KEY: &str = "detail";
+
+/// The pairs of `source` except those named `key`
+pub(crate) struct Without<'a> {
+    pub(crate) source: &'a dyn Source,
+    pub(crate) key: &'a str,
+}
+
+impl Source for Without<'_> {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), kv::Error> {
+        self.source.visit(&mut SkipKey {
+            key: self.key,
+            visitor,
+        })
+    }
+}
+
+/// Passes the pairs on to `visitor`, except those named `key`
+struct SkipKey<'a, 'v, 'kvs> {
+    key: &'a str,
+    visitor: &'v mut dyn VisitSource<'kvs>,
+}
+
+impl<'kvs> VisitSource<'kvs> for SkipKey<'_, '_, 'kvs> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        if key.as_str() == self.key {
+            return Ok(());
+        }
+        self.visitor.visit_pair(key, value)
+    }
+}
+
+/// The key of the duration the slow log checks, see `LoggingConfig::set_slow_log_threshold`
+const DURATION_KEY: &str = "duration_ms";
+
+/// The `duration_ms` key-value of `source` in milliseconds, if it is a number
+pub(crate) fn duration_ms(source: &dyn Source) -> Option<f64> {
+    let value = source.get(Key::from_str(DURATION_KEY))?;
+    value
+        .to_f64()
+        .or_else(|| value.to_borrowed_str()?.trim().parse().ok())
+        .filter(|ms: &f64| ms.is_finite())
+}
+
+/// The key the correlation id is written under
+const CORRELATION_KEY: &str = "correlation_id";
+
+thread_local! {
+    static CORRELATION_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Set the correlation id of the calling thread, added as a
+/// `correlation_id` key-value to every record it logs until it is cleared or
+/// replaced<br><br>
+/// Meant for tagging the lines of one message, frame or request while a
+/// thread handles it:<br>
+/// `set_correlation_id(&frame.id); decode(&frame); clear_correlation_id();`<br><br>
+/// The field is written in human, JSON and CSV output after the record's own
+/// key-values and before the global fields. A record key-value named
+/// `correlation_id` takes precedence. The id is thread-local, so it does not
+/// follow async tasks that move between the threads of an executor, nor work
+/// handed to other threads; read it with `correlation_id` and set it again
+/// where the work continues. Records buffered by `capture_early_logs` are
+/// formatted later on another thread and go without it.
+pub fn set_correlation_id(id: &str) {
+    CORRELATION_ID.with(|current| *current.borrow_mut() = Some(id.to_string()));
+}
+
+/// Remove the correlation id of the calling thread, see `set_correlation_id`
+pub fn clear_correlation_id() {
+    CORRELATION_ID.with(|current| *current.borrow_mut() = None);
+}
+
+/// The correlation id of the calling thread, to carry it over to another
+/// thread or task, see `set_correlation_id`
+#[must_use]
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.with(|current| current.borrow().clone())
+}
+
+/// Render `error` followed by each error in its `source` chain<br><br>
+/// `error!("Decoding failed: {}", error_chain(&err));` logs
+/// `Decoding failed: bad frame; caused by: checksum mismatch`<br><br>
+/// Errors attached as key-values with the `err` capture,
+/// `error!(err:err = err; "Decoding failed")`, are rendered with their
+/// chain too: as one quoted `err="..."` value in human output and as an
+/// array of messages, outermost first, in JSON.
+pub fn error_chain(error: &(dyn Error + 'static)) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        let _ = write!(chain, "; caused by: {error}");
+        source = error.source();
+    }
+    chain
+}
+
+/// Visit the pairs of `source`, then the correlation id of the thread and the
+/// pairs of `globals` whose key is not in `source` or taken by the correlation id
+fn visit_with_globals<'kvs, V>(
+    source: &'kvs dyn Source,
+    globals: &'kvs [(String, String)],
+    visitor: &mut V,
+) -> Result<(), kv::Error>
+where
+    V: for<'a> VisitSource<'a>,
+{
+    source.visit(visitor)?;
+    CORRELATION_ID.with(|id| {
+        let id = id.borrow();
+        let correlation = Key::from_str(CORRELATION_KEY);
+        let has_id = id.is_some() && source.get(correlation.clone()).is_none();
+        if let (true, Some(id)) = (has_id, id.as_deref()) {
+            visitor.visit_pair(correlation, Value::from(id))?;
+        }
+        for (key, value) in globals {
+            let taken = has_id && key == CORRELATION_KEY;
+            let key = Key::from_str(key);
+            if !taken && source.get(key.clone()).is_none() {
+                visitor.visit_pair(key, Value::from(value.as_str()))?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Whether a human `key=value` value has to be quoted to be read back unambiguously
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '=')
+}
+
+/// The human text of `value`, quoted and escaped if needed
+fn human_value(value: &Value) -> String {
+    let value = match value.to_borrowed_error() {
+        Some(error) => error_chain(error),
+        None => value.to_string(),
+    };
+    if needs_quotes(&value) {
+        format!("{value:?}")
+    } else {
+        value
+    }
+}
+
+struct HumanVisitor<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for HumanVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let _ = write!(self.0, " {key}={}", human_value(&value));
+        Ok(())
+    }
+}
+
+/// Render `source` and the global fields as ` key=value` pairs for human
+/// output. Values with spaces, quotes or control characters are quoted and escaped.
+pub(crate) fn human_fields(source: &dyn Source) -> String {
+    human_fields_with(source, global_fields())
+}
+
+fn human_fields_with(source: &dyn Source, globals: &[(String, String)]) -> String {
+    let mut fields = String::new();
+    let _ = visit_with_globals(source, globals, &mut HumanVisitor(&mut fields));
+    fields
+}
+
+struct PairVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for PairVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), human_value(&value)));
+        Ok(())
+    }
+}
+
+/// The keys and human values of `source` and the global fields, as rendered
+/// by `human_fields`, for laying the pairs out separately
+pub(crate) fn human_pairs(source: &dyn Source) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let _ = visit_with_globals(source, global_fields(), &mut PairVisitor(&mut pairs));
+    pairs
+}
+
+/// Writes a single value as JSON, keeping numbers, booleans and null unquoted
+struct JsonValue<'a>(&'a mut Vec<u8>);
+
+impl JsonValue<'_> {
+    fn raw(&mut self, value: impl std::fmt::Display) -> Result<(), kv::Error> {
+        self.0.extend_from_slice(value.to_string().as_bytes());
+        Ok(())
+    }
+
+    fn string(&mut self, value: &str) -> Result<(), kv::Error> {
+        write_json_string(self.0, value).map_err(|_| kv::Error::msg("write failed"))
+    }
+}
+
+impl<'v> VisitValue<'v> for JsonValue<'_> {
+    fn visit_any(&mut self, value: Value) -> Result<(), kv::Error> {
+        self.string(&value.to_string())
+    }
+
+    fn visit_null(&mut self) -> Result<(), kv::Error> {
+        self.raw("null")
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), kv::Error> {
+        self.raw(value)
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), kv::Error> {
+        self.raw(value)
+    }
+
+    fn visit_u128(&mut self, value: u128) -> Result<(), kv::Error> {
+        self.raw(value)
+    }
+
+    fn visit_i128(&mut self, value: i128) -> Result<(), kv::Error> {
+        self.raw(value)
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), kv::Error> {
+        // JSON has no representation for NaN or infinity
+        if value.is_finite() {
+            self.raw(value)
+        } else {
+            self.string(&value.to_string())
+        }
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), kv::Error> {
+        self.raw(value)
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), kv::Error> {
+        self.string(value)
+    }
+
+    fn visit_error(&mut self, error: &(dyn Error + 'static)) -> Result<(), kv::Error> {
+        self.0.push(b'[');
+        self.string(&error.to_string())?;
+        let mut source = error.source();
+        while let Some(error) = source {
+            self.0.push(b',');
+            self.string(&error.to_string())?;
+            source = error.source();
+        }
+        self.0.push(b']');
+        Ok(())
+    }
+
+    fn visit_borrowed_error(&mut self, error: &'v (dyn Error + 'static)) -> Result<(), kv::Error> {
+        self.visit_error(error)
+    }
+}
+
+struct JsonVisitor<'a>(&'a mut Vec<u8>);
+
+impl<'kvs> VisitSource<'kvs> for JsonVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push(b',');
+        write_json_string(self.0, key.as_str()).map_err(|_| kv::Error::msg("write failed"))?;
+        self.0.push(b':');
+        value.visit(JsonValue(self.0))
+    }
+}
+
+/// Render `source` and the global fields as `,"key":value` members to append
+/// to a JSON object
+pub(crate) fn json_fields(source: &dyn Source) -> Vec<u8> {
+    json_fields_with(source, global_fields())
+}
+
+fn json_fields_with(source: &dyn Source, globals: &[(String, String)]) -> Vec<u8> {
+    let mut fields = Vec::new();
+    let _ = visit_with_globals(source, globals, &mut JsonVisitor(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fmt;
+
+    #[test]
+    fn test_build_info() {
+        let info = BuildInfo::new("1.2.0", "1a2b3c4", "");
+        assert_eq!(info.fields(), [("version", "1.2.0"), ("commit", "1a2b3c4")]);
+        assert_eq!(
+            human_fields_with(&info.fields(), &[]),
+            " version=1.2.0 commit=1a2b3c4"
+        );
+    }
+
+    /// An error with an optional cause, to build chains in tests
+    #[derive(Debug)]
+    struct Layer(&'static str, Option<Box<Layer>>);
+
+    impl fmt::Display for Layer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl Error for Layer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.1
+                .as_deref()
+                .map(|cause| cause as &(dyn Error + 'static))
+        }
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let error = Layer(
+            "bad frame",
+            Some(Box::new(Layer(
+                "checksum mismatch",
+                Some(Box::new(Layer("short read", None))),
+            ))),
+        );
+        assert_eq!(
+            error_chain(&error),
+            "bad frame; caused by: checksum mismatch; caused by: short read"
+        );
+        assert_eq!(error_chain(&Layer("timeout", None)), "timeout");
+
+        let fields: &[(&str, Value)] = &[("err", Value::from_dyn_error(&error))];
+        assert_eq!(
+            human_fields(&fields),
+            " err=\"bad frame; caused by: checksum mismatch; caused by: short read\""
+        );
+        assert_eq!(
+            String::from_utf8(json_fields(&fields)).unwrap(),
+            ",\"err\":[\"bad frame\",\"checksum mismatch\",\"short read\"]"
+        );
+    }
+
+    #[test]
+    fn test_human_fields() {
+        let fields: &[(&str, Value)] = &[
+            ("freq", Value::from(1090)),
+            ("callsign", Value::from("N12345")),
+            ("note", Value::from("two words")),
+            ("empty", Value::from("")),
+        ];
+        assert_eq!(
+            human_fields(&fields),
+            " freq=1090 callsign=N12345 note=\"two words\" empty=\"\""
+        );
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let fields: &[(&str, Value)] = &[
+            ("freq", Value::from(1090)),
+            ("gain", Value::from(-1.5)),
+            ("locked", Value::from(true)),
+            ("callsign", Value::from("N1\"2")),
+            ("nothing", Value::null()),
+        ];
+        assert_eq!(
+            String::from_utf8(json_fields(&fields)).unwrap(),
+            ",\"freq\":1090,\"gain\":-1.5,\"locked\":true,\"callsign\":\"N1\\\"2\",\"nothing\":null"
+        );
+    }
+
+    #[test]
+    fn test_correlation_id() {
+        let globals = [(String::from("correlation_id"), String::from("unused"))];
+        let fields: &[(&str, Value)] = &[("freq", Value::from(1090))];
+        assert_eq!(
+            human_fields_with(&fields, &globals),
+            " freq=1090 correlation_id=unused"
+        );
+
+        set_correlation_id("frame-17");
+        assert_eq!(correlation_id().as_deref(), Some("frame-17"));
+        assert_eq!(
+            human_fields_with(&fields, &globals),
+            " freq=1090 correlation_id=frame-17"
+        );
+        assert_eq!(
+            String::from_utf8(json_fields(&fields)).unwrap(),
+            ",\"freq\":1090,\"correlation_id\":\"frame-17\""
+        );
+        // the record's own key-value wins, and other threads have no id
+        let own: &[(&str, Value)] = &[("correlation_id", Value::from("own"))];
+        assert_eq!(human_fields(&own), " correlation_id=own");
+        std::thread::spawn(|| assert_eq!(human_fields(&[("freq", 1090)]), " freq=1090"))
+            .join()
+            .unwrap();
+
+        clear_correlation_id();
+        assert_eq!(correlation_id(), None);
+        assert_eq!(human_fields(&fields), " freq=1090");
+    }
+
+    #[test]
+    fn test_global_fields() {
+        let globals = [
+            (String::from("region"), String::from("eu west")),
+            (String::from("freq"), String::from("unused")),
+        ];
+        let fields: &[(&str, Value)] = &[("freq", Value::from(1090))];
+        assert_eq!(
+            human_fields_with(&fields, &globals),
+            " freq=1090 region=\"eu west\""
+        );
+        assert_eq!(
+            String::from_utf8(json_fields_with(&fields, &globals)).unwrap(),
+            ",\"freq\":1090,\"region\":\"eu west\""
+        );
+    }
+}
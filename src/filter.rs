@@ -0,0 +1,233 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Per-module level filtering, shared by every output
+
+use log::LevelFilter;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// Levels per module, for `LoggingConfig::set_module_filter`<br><br>
+/// Parsed from comma separated directives in the syntax of env_logger's
+/// `RUST_LOG`, e.g. `"warn,receiver::rf=trace,hyper=off"`:
+/// - `module=level` sets the level of targets starting with `module`
+/// - a bare `module` enables every level for it
+/// - a bare `level` replaces the level set with `LoggingConfig::set_level`
+///
+/// Level names are case insensitive and include `off`. When several modules
+/// match a target the longest one wins, and targets matching none use the
+/// default level. Every output uses this one filter, so a directive behaves
+/// the same whether records go to stderr, a file, logcat or the browser
+/// console. A record pays for a scan of the directives, longest first, when
+/// it is checked.<br><br>
+/// `ModuleFilter::new().set_module("receiver::rf", LevelFilter::Trace)`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ModuleFilter {
+    default: Option<LevelFilter>,
+    /// Sorted by module length, longest first
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl ModuleFilter {
+    /// A filter without directives, leaving every target at the default level
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the level of targets matching no module, replacing the level set
+    /// with `LoggingConfig::set_level`
+    #[must_use]
+    pub fn set_default(mut self, level: LevelFilter) -> Self {
+        self.default = Some(level);
+        self
+    }
+
+    /// Set the level of targets starting with `module`, replacing an earlier
+    /// level for the same module
+    #[must_use]
+    pub fn set_module(mut self, module: &str, level: LevelFilter) -> Self {
+        self.modules.retain(|(known, _)| known != module);
+        self.modules.push((module.to_string(), level));
+        self.modules
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        self
+    }
+
+    #[must_use]
+    pub fn default_level(&self) -> Option<LevelFilter> {
+        self.default
+    }
+
+    /// The module directives, longest module first
+    #[must_use]
+    pub fn modules(&self) -> &[(String, LevelFilter)] {
+        &self.modules
+    }
+
+    /// The level for records of `target`, with `fallback` used when neither
+    /// a module nor the default level is set
+    #[must_use]
+    pub fn level_for(&self, target: &str, fallback: LevelFilter) -> LevelFilter {
+        self.module_level(target)
+            .unwrap_or_else(|| self.default.unwrap_or(fallback))
+    }
+
+    /// The level of the longest module matching `target`, if any
+    pub(crate) fn module_level(&self, target: &str) -> Option<LevelFilter> {
+        self.modules
+            .iter()
+            .find(|(module, _)| target.starts_with(module.as_str()))
+            .map(|(_, level)| *level)
+    }
+
+    /// The most verbose level any target can have
+    pub(crate) fn max_level(&self, fallback: LevelFilter) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default.unwrap_or(fallback), Ord::max)
+    }
+}
+
+/// The module filter of a running logger, shared by its outputs and replaced
+/// through `LoggingGuard::set_module_filter`
+#[derive(Debug)]
+pub(crate) struct LiveFilter(RwLock<ModuleFilter>);
+
+impl LiveFilter {
+    pub(crate) fn new(filter: ModuleFilter) -> Self {
+        Self(RwLock::new(filter))
+    }
+
+    /// Run `f` with the current filter, which is not replaced meanwhile
+    pub(crate) fn read<R>(&self, f: impl FnOnce(&ModuleFilter) -> R) -> R {
+        f(&self
+            .0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    pub(crate) fn set(&self, filter: ModuleFilter) {
+        *self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = filter;
+    }
+}
+
+/// A module filter that could not be parsed, see `ModuleFilter`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError(String);
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid module filter: {}", self.0)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl FromStr for ModuleFilter {
+    type Err = FilterError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut filter = ModuleFilter::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            filter = match directive.split_once('=') {
+                Some((module, level)) => {
+                    let module = module.trim();
+                    if module.is_empty() {
+                        return Err(FilterError(format!("missing module in `{directive}`")));
+                    }
+                    let level = level
+                        .trim()
+                        .parse()
+                        .map_err(|_| FilterError(format!("unknown level in `{directive}`")))?;
+                    filter.set_module(module, level)
+                }
+                None => match directive.parse() {
+                    Ok(level) => filter.set_default(level),
+                    Err(_) => filter.set_module(directive, LevelFilter::Trace),
+                },
+            };
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter() {
+        let filter: ModuleFilter = "warn, receiver::rf=TRACE,hyper=off,receiver"
+            .parse()
+            .unwrap();
+        assert_eq!(filter.default_level(), Some(LevelFilter::Warn));
+        assert_eq!(
+            filter.modules(),
+            [
+                (String::from("receiver::rf"), LevelFilter::Trace),
+                (String::from("receiver"), LevelFilter::Trace),
+                (String::from("hyper"), LevelFilter::Off),
+            ]
+        );
+        assert_eq!(
+            filter,
+            ModuleFilter::new()
+                .set_module("hyper", LevelFilter::Off)
+                .set_module("receiver", LevelFilter::Trace)
+                .set_module("receiver::rf", LevelFilter::Trace)
+                .set_default(LevelFilter::Warn)
+        );
+        assert_eq!("".parse::<ModuleFilter>().unwrap(), ModuleFilter::new());
+
+        for invalid in ["receiver=loud", "=debug", "receiver=", "info,rf=1"] {
+            assert!(invalid.parse::<ModuleFilter>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_level_for() {
+        let filter = ModuleFilter::new()
+            .set_module("receiver", LevelFilter::Debug)
+            .set_module("receiver::rf", LevelFilter::Trace)
+            .set_module("receiver::rf", LevelFilter::Error);
+        assert_eq!(
+            filter.level_for("receiver::rf::tuner", LevelFilter::Info),
+            LevelFilter::Error
+        );
+        assert_eq!(
+            filter.level_for("receiver::net", LevelFilter::Info),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            filter.level_for("hyper", LevelFilter::Info),
+            LevelFilter::Info
+        );
+        assert_eq!(filter.max_level(LevelFilter::Info), LevelFilter::Debug);
+
+        let filter = filter.set_default(LevelFilter::Off);
+        assert_eq!(
+            filter.level_for("hyper", LevelFilter::Info),
+            LevelFilter::Off
+        );
+        assert_eq!(filter.max_level(LevelFilter::Trace), LevelFilter::Debug);
+    }
+}
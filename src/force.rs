@@ -0,0 +1,166 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Records logged past the level filters, for `log_forced`
+
+use crate::LoggingConfig;
+use log::{Level, Log, Metadata, Record};
+use std::cell::Cell;
+use std::fmt;
+
+thread_local! {
+    /// Set while the calling thread logs a record with `log_forced`
+    static FORCED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the record being logged on this thread was logged with `log_forced`
+pub(crate) fn forced() -> bool {
+    FORCED.with(Cell::get)
+}
+
+/// Clears the forced flag when dropped, also if a logger panics
+struct ClearForced;
+
+impl Drop for ClearForced {
+    fn drop(&mut self) {
+        FORCED.with(|forced| forced.set(false));
+    }
+}
+
+/// Log a record at `level` whatever the configured levels, e.g. a debug
+/// detail that is wanted whenever a rare condition hits<br><br>
+/// The record skips the `log` maximum level, the level and module filters of
+/// every output and sink, the init phase of `LoggingConfig::set_init_level`
+/// and the sampling of `LoggingConfig::set_sample_rate`. It is written with
+/// its own level. The message filters, such as
+/// `LoggingConfig::set_deny_messages`, still apply, as do filters added with
+/// `LoggingConfig::set_builder_hook` and the decision of the user's logger of
+/// `LoggingConfig::set_extra_logger`. Usually called through the `log_forced!`
+/// macro.<br><br>
+/// This is an escape hatch: every forced record is one the operator chose not
+/// to see, so forcing more than the odd message defeats the point of levels.
+pub fn log_forced(level: Level, target: &str, args: fmt::Arguments) {
+    FORCED.with(|forced| forced.set(true));
+    let _clear = ClearForced;
+    log::logger().log(
+        &Record::builder()
+            .args(args)
+            .level(level)
+            .target(target)
+            .build(),
+    );
+}
+
+/// Log a message past the level filters, see `log_forced`<br><br>
+/// `log_forced!(Level::Debug, "tuner state after overflow: {:?}", state);`
+#[macro_export]
+macro_rules! log_forced {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log_forced($level, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Whether a record with `metadata` passes the level and module filter of
+/// `config`, or is forced
+pub(crate) fn enabled(config: &LoggingConfig, metadata: &Metadata) -> bool {
+    metadata.level() <= config.gate_level_for(metadata.target()) || forced()
+}
+
+/// Applies the level and module filters in front of an env_logger logger,
+/// whose own filter lets every level through so forced records reach it
+pub(crate) struct LevelGate {
+    inner: Box<dyn Log>,
+    config: LoggingConfig,
+}
+
+impl LevelGate {
+    pub(crate) fn new(inner: Box<dyn Log>, config: &LoggingConfig) -> Self {
+        Self {
+            inner,
+            config: config.clone(),
+        }
+    }
+}
+
+impl Log for LevelGate {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        enabled(&self.config, metadata) && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if enabled(&self.config, record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Collect {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push(format!("{}", record.args()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_level_gate() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let gate = LevelGate::new(
+            Box::new(Collect(lines.clone())),
+            &LoggingConfig::new().set_module_filter("warn,receiver=trace".parse().unwrap()),
+        );
+        for (target, level) in [("app", Level::Info), ("receiver::rf", Level::Trace)] {
+            gate.log(
+                &Record::builder()
+                    .args(format_args!("{target}"))
+                    .level(level)
+                    .target(target)
+                    .build(),
+            );
+        }
+        let debug = Metadata::builder()
+            .level(Level::Debug)
+            .target("app")
+            .build();
+        assert!(!gate.enabled(&debug));
+
+        FORCED.with(|forced| forced.set(true));
+        let _clear = ClearForced;
+        assert!(gate.enabled(&debug));
+        gate.log(
+            &Record::builder()
+                .args(format_args!("forced"))
+                .metadata(debug)
+                .build(),
+        );
+        assert_eq!(*lines.lock().unwrap(), ["receiver::rf", "forced"]);
+    }
+}
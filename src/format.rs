@@ -0,0 +1,3139 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use crate::config::DEFAULT_TIME_FORMAT;
+use crate::recent::RecentLines;
+use crate::stats::Stats;
+use crate::writer::LatencyState;
+use crate::{binary, fields, heartbeat, stats};
+use crate::{
+    ColorTheme, EmptyMessage, LevelCase, LevelLabel, LogFormat, LoggingConfig, TimezoneSuffix,
+};
+use anstyle::Style;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, Utc};
+use log::kv::Key;
+use log::{Level, Record};
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Process wide count of records written with sequence numbers enabled
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Set while a sink of `add_sink` or the extra logger of `set_extra_logger`
+    /// is given a record the outputs already counted, see `as_secondary`
+    static SECONDARY: Cell<bool> = const { Cell::new(false) };
+    /// The sequence number the outputs gave the record being logged on this
+    /// thread, for the sinks and the extra logger
+    static RECORD_SEQUENCE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Run `f`, a sink of `add_sink` or the extra logger handling a record,
+/// without the side effects the outputs already had: formatters within it neither count the record in the
+/// stats and metrics, nor write status lines, nor note it for the heartbeat,
+/// and they reuse the sequence number of the outputs
+pub(crate) fn as_secondary<R>(f: impl FnOnce() -> R) -> R {
+    /// Clears the flag when dropped, also if the logger panics
+    struct ClearSecondary;
+
+    impl Drop for ClearSecondary {
+        fn drop(&mut self) {
+            SECONDARY.with(|secondary| secondary.set(false));
+        }
+    }
+
+    SECONDARY.with(|secondary| secondary.set(true));
+    let _clear = ClearSecondary;
+    f()
+}
+
+/// Whether the record is being logged to a sink or the extra logger, see
+/// `as_secondary`
+fn secondary() -> bool {
+    SECONDARY.with(Cell::get)
+}
+
+/// The sequence number of the record being written
+fn next_sequence() -> u64 {
+    if secondary() {
+        if let Some(sequence) = RECORD_SEQUENCE.with(Cell::get) {
+            return sequence;
+        }
+    }
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1;
+    RECORD_SEQUENCE.with(|record| record.set(Some(sequence)));
+    sequence
+}
+
+/// When the first formatter with `set_uptime` or `set_elapsed_time` was
+/// created, the reference of the uptime field and elapsed timestamps
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// The time since `START`
+fn uptime() -> Duration {
+    START.get_or_init(Instant::now).elapsed()
+}
+
+/// Target of the records written by `log_fatal_and_flush`
+pub(crate) const FATAL_TARGET: &str = "sdre_rust_logging::fatal";
+/// Target of the banner lines of `LoggingConfig::set_banners`
+pub(crate) const BANNER_TARGET: &str = "sdre_rust_logging::banner";
+/// Target of the record `LoggingGuard` logs when it is dropped, which writes
+/// the count of a run of repeats still held back and nothing else
+pub(crate) const FLUSH_TARGET: &str = "sdre_rust_logging::flush";
+
+/// The chrono format string for human timestamps, without the timezone suffix.
+/// An invalid format string falls back to the default, as chrono would otherwise
+/// fail every time a record is written.
+fn resolve_base_time_format(config: &LoggingConfig) -> String {
+    let mut format = config.time_format();
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        format = DEFAULT_TIME_FORMAT;
+    }
+
+    let separated;
+    if format == DEFAULT_TIME_FORMAT && config.date_time_separator() != 'T' {
+        let separator = match config.date_time_separator() {
+            '%' => String::from("%%"),
+            separator => separator.to_string(),
+        };
+        separated = format!("%Y-%m-%d{separator}%H:%M:%S");
+        format = &separated;
+    }
+
+    format.to_string()
+}
+
+/// The chrono format string for local human timestamps, including the timezone
+/// suffix.
+fn resolve_time_format(config: &LoggingConfig) -> String {
+    let format = resolve_base_time_format(config);
+    if config.dual_timestamps() {
+        return format!("{format}%:z");
+    }
+    match config.timezone_suffix() {
+        TimezoneSuffix::None => format,
+        TimezoneSuffix::Offset => format!("{format}%:z"),
+    }
+}
+
+/// The label for the level of `record`
+fn level_name(record: &Record) -> &'static str {
+    if record.target() == FATAL_TARGET {
+        "FATAL"
+    } else {
+        record.level().as_str()
+    }
+}
+
+/// Cut `message` down to at most `max_len` bytes, on a char boundary and
+/// before an ANSI escape sequence the limit would split, and note how many
+/// bytes were dropped.
+fn truncate_message(message: &mut String, max_len: usize) {
+    if message.len() <= max_len {
+        return;
+    }
+
+    let mut cut = max_len;
+    while !message.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    if let Some(start) = message[..cut].rfind('\x1b') {
+        if ansi_sequence_len(&message[start..]).map_or(true, |len| start + len > cut) {
+            cut = start;
+        }
+    }
+
+    let dropped = message.len() - cut;
+    message.truncate(cut);
+    message.push_str(&format!("… [{dropped} bytes truncated]"));
+}
+
+/// Whether `message` counts as empty for `set_empty_message`: it is empty or
+/// only whitespace, such as a stray `"   "` or `"\n"`
+fn is_blank(message: &str) -> bool {
+    message.trim().is_empty()
+}
+
+/// Replace control characters with a printable escape
+fn escape_control_characters(message: &str) -> String {
+    let mut escaped = String::with_capacity(message.len());
+    for c in message.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() && (c as u32) < 0x80 => {
+                escaped.push_str(&format!("\\x{:02x}", c as u32));
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Look up the hostname of this machine for the `host` field
+fn resolve_hostname() -> String {
+    if let Ok(hostname) = std::env::var("HOSTNAME") {
+        if !hostname.is_empty() {
+            return hostname;
+        }
+    }
+
+    for path in ["/proc/sys/kernel/hostname", "/etc/hostname"] {
+        if let Ok(hostname) = std::fs::read_to_string(path) {
+            let hostname = hostname.trim();
+            if !hostname.is_empty() {
+                return hostname.to_string();
+            }
+        }
+    }
+
+    String::from("localhost")
+}
+
+/// Remove every ANSI escape sequence from `message`: CSI sequences such as
+/// styles and cursor movement, OSC sequences such as titles and hyperlinks, and
+/// two character escapes. An unterminated sequence is removed up to the end.
+fn strip_ansi_sequences(message: &str) -> String {
+    let mut plain = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    plain
+}
+
+/// The length in bytes of the ANSI escape sequence `text` starts with, as
+/// `strip_ansi_sequences` reads it, or `None` if it is not terminated
+fn ansi_sequence_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+    match chars.next() {
+        Some((_, '[')) => chars
+            .find(|(_, c)| ('\x40'..='\x7e').contains(c))
+            .map(|(index, c)| index + c.len_utf8()),
+        Some((_, ']')) => {
+            let mut previous = None;
+            chars
+                .find(|&(_, c)| {
+                    let end = c == '\x07' || (c == '\\' && previous == Some('\x1b'));
+                    previous = Some(c);
+                    end
+                })
+                .map(|(index, _)| index + 1)
+        }
+        Some((index, c)) => Some(index + c.len_utf8()),
+        None => None,
+    }
+}
+
+/// Remove the ANSI styling from `output`
+pub(crate) fn strip_styles(output: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = output.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// The number of characters of `text` shown on a terminal, skipping ANSI styles
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// `message` with each match of the `highlights` patterns styled, restoring
+/// `line_style` after each, see `LoggingConfig::add_highlight`
+fn highlight_matches(message: &str, highlights: &[(String, Style)], line_style: Style) -> String {
+    let mut highlighted = String::with_capacity(message.len());
+    let mut rest = message;
+    loop {
+        let next = highlights
+            .iter()
+            .filter(|(pattern, _)| !pattern.is_empty())
+            .filter_map(|(pattern, style)| {
+                rest.find(pattern.as_str()).map(|at| (at, pattern, style))
+            })
+            .min_by_key(|(at, _, _)| *at);
+        let Some((at, pattern, style)) = next else {
+            highlighted.push_str(rest);
+            return highlighted;
+        };
+        let end = at + pattern.len();
+        highlighted.push_str(&format!(
+            "{}{style}{}{style:#}{line_style}",
+            &rest[..at],
+            &rest[at..end]
+        ));
+        rest = &rest[end..];
+    }
+}
+
+/// Wrap `text` at `column`, given that the first line already has `used`
+/// characters before it. Continuation lines are indented by `indent` spaces.
+fn wrap_text(text: &str, column: usize, used: usize, indent: usize) -> String {
+    let column = column.max(1);
+    let mut wrapped = String::with_capacity(text.len());
+    let mut width = used;
+    let newline = |wrapped: &mut String, width: &mut usize| {
+        wrapped.push('\n');
+        wrapped.extend(std::iter::repeat(' ').take(indent));
+        *width = indent;
+    };
+
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            newline(&mut wrapped, &mut width);
+        }
+        for (position, word) in line.split(' ').enumerate() {
+            let mut length = word.chars().count();
+            if position > 0 {
+                if width + 1 + length <= column {
+                    wrapped.push(' ');
+                    width += 1;
+                } else {
+                    newline(&mut wrapped, &mut width);
+                }
+            }
+
+            // words longer than the space left are split mid word
+            let mut rest = word;
+            while width + length > column {
+                let fits = column.saturating_sub(width);
+                if fits > 0 {
+                    let split = rest
+                        .char_indices()
+                        .nth(fits)
+                        .map_or(rest.len(), |(at, _)| at);
+                    wrapped.push_str(&rest[..split]);
+                    rest = &rest[split..];
+                    length -= fits;
+                }
+                newline(&mut wrapped, &mut width);
+            }
+            wrapped.push_str(rest);
+            width += length;
+        }
+    }
+    wrapped
+}
+
+/// Write `value` as a CSV field, quoted if it contains a comma, quote or line break
+fn write_csv_field<W: Write + ?Sized>(buf: &mut W, value: &str) -> io::Result<()> {
+    if value.contains([',', '"', '\r', '\n']) {
+        write!(buf, "\"{}\"", value.replace('"', "\"\""))
+    } else {
+        buf.write_all(value.as_bytes())
+    }
+}
+
+/// Indent a JSON object with one member or element per line, two spaces per
+/// level of nesting. Empty objects and arrays stay on one line.
+fn pretty_json(line: &[u8]) -> Vec<u8> {
+    fn newline(pretty: &mut Vec<u8>, depth: usize) {
+        pretty.push(b'\n');
+        pretty.extend(std::iter::repeat(b' ').take(depth * 2));
+    }
+
+    let mut pretty = Vec::with_capacity(line.len() * 2);
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut depth = 0;
+    for (index, &byte) in line.iter().enumerate() {
+        if in_string {
+            pretty.push(byte);
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => {
+                in_string = true;
+                pretty.push(byte);
+            }
+            b'{' | b'[' => {
+                pretty.push(byte);
+                depth += 1;
+                if !matches!(line.get(index + 1), Some(b'}' | b']')) {
+                    newline(&mut pretty, depth);
+                }
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                if !matches!(pretty.last(), Some(b'{' | b'[')) {
+                    newline(&mut pretty, depth);
+                }
+                pretty.push(byte);
+            }
+            b',' => {
+                pretty.push(byte);
+                newline(&mut pretty, depth);
+            }
+            b':' => pretty.extend_from_slice(b": "),
+            _ => pretty.push(byte),
+        }
+    }
+    pretty
+}
+
+pub(crate) fn write_json_string<W: Write + ?Sized>(buf: &mut W, value: &str) -> io::Result<()> {
+    buf.write_all(b"\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => buf.write_all(b"\\\"")?,
+            '\\' => buf.write_all(b"\\\\")?,
+            '\n' => buf.write_all(b"\\n")?,
+            '\r' => buf.write_all(b"\\r")?,
+            '\t' => buf.write_all(b"\\t")?,
+            c if c.is_control() => write!(buf, "\\u{:04x}", c as u32)?,
+            c => write!(buf, "{c}")?,
+        }
+    }
+    buf.write_all(b"\"")
+}
+
+/// `value` cut or padded with spaces to `width` characters
+fn fit_column(value: &str, width: usize) -> String {
+    let mut column: String = value.chars().take(width).collect();
+    let len = column.chars().count();
+    if len < width {
+        column.extend(std::iter::repeat(' ').take(width - len));
+    }
+    column
+}
+
+/// Write `record` as the 0.3 releases did, for `LogFormat::LegacyV1`
+fn write_legacy_v1<W: Write + ?Sized>(
+    buf: &mut W,
+    record: &Record,
+    now: DateTime<Utc>,
+) -> io::Result<()> {
+    let theme = ColorTheme::default();
+    let level_style = theme.level(record.level());
+    let time_style = theme.timestamp;
+    writeln!(
+        buf,
+        "[{level_style}{: <5}{level_style:#}][{time_style}{}{time_style:#}]{}",
+        record.level(),
+        now.with_timezone(&Local).format(DEFAULT_TIME_FORMAT),
+        record.args()
+    )
+}
+
+/// The styled timestamp of a human line, formatted straight into the output
+struct Timestamp<'a> {
+    now: DateTime<Utc>,
+    format: &'a str,
+    utc_format: Option<&'a str>,
+    /// Set with `set_rfc3339_timestamps`, replacing both formats
+    rfc3339: Option<SecondsFormat>,
+    /// Set with `set_elapsed_time`, replacing the wall clock entirely
+    elapsed: Option<Duration>,
+    /// Width and fractional digits of `set_epoch_timestamps`, replacing all formats
+    epoch: Option<(usize, usize)>,
+    /// Set with `set_utc_timestamps`, writing `format` in UTC
+    utc: bool,
+    style: Style,
+}
+
+/// `now` in the timezone of human timestamps, see `set_utc_timestamps`
+fn wall_time(now: DateTime<Utc>, utc: bool) -> DateTime<FixedOffset> {
+    if utc {
+        now.fixed_offset()
+    } else {
+        now.with_timezone(&Local).fixed_offset()
+    }
+}
+
+impl fmt::Display for Timestamp<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = self.style;
+        if let Some(elapsed) = self.elapsed {
+            let seconds = elapsed.as_secs();
+            return write!(f, "{style}{:02}:{:02}{style:#}", seconds / 60, seconds % 60);
+        }
+        if let Some((width, digits)) = self.epoch {
+            write!(f, "{style}{:0width$}", self.now.timestamp())?;
+            if digits > 0 {
+                let fraction = self.now.timestamp_subsec_nanos() / 10u32.pow(9 - digits as u32);
+                write!(f, ".{fraction:0digits$}")?;
+            }
+            return write!(f, "{style:#}");
+        }
+        let local = wall_time(self.now, self.utc);
+        if let Some(seconds) = self.rfc3339 {
+            write!(f, "{style}{}", local.to_rfc3339_opts(seconds, true))?;
+            if self.utc_format.is_some() {
+                write!(f, " / {}", self.now.to_rfc3339_opts(seconds, true))?;
+            }
+            return write!(f, "{style:#}");
+        }
+        write!(f, "{style}{}", local.format(self.format))?;
+        if let Some(utc_format) = self.utc_format {
+            write!(f, " / {}", self.now.format(utc_format))?;
+        }
+        write!(f, "{style:#}")
+    }
+}
+
+/// The prefix fields of a human line, ready for the message to follow
+struct Prefix<'a> {
+    fields: &'a [&'a dyn fmt::Display],
+    brackets: bool,
+}
+
+impl fmt::Display for Prefix<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, field) in self.fields.iter().enumerate() {
+            if self.brackets {
+                write!(f, "[{field}]")?;
+            } else if index == 0 {
+                write!(f, "{field}")?;
+            } else {
+                write!(f, " {field}")?;
+            }
+        }
+        if self.brackets {
+            Ok(())
+        } else {
+            f.write_str(" ")
+        }
+    }
+}
+
+/// The last line written when collapsing repeated lines
+#[derive(Default)]
+struct DedupeState {
+    /// Level, target, message and fields of the last line
+    key: String,
+    /// How many times the last line was repeated and not written
+    repeats: u64,
+    /// The most recent repeat, written with a count when a different line arrives
+    pending: Vec<u8>,
+}
+
+/// Write the last repeat held back in `state` with the count of its run, if
+/// there is one, and end the run
+fn write_pending<W: Write + ?Sized>(buf: &mut W, state: &mut DedupeState) -> io::Result<()> {
+    if state.repeats == 0 {
+        return Ok(());
+    }
+    let pending = std::mem::take(&mut state.pending);
+    let body = pending.strip_suffix(b"\n").unwrap_or(&pending);
+    buf.write_all(body)?;
+    writeln!(buf, " (x{})", state.repeats + 1)?;
+    state.repeats = 0;
+    Ok(())
+}
+
+/// The last record written for a level with a cooldown
+#[derive(Default)]
+struct CooldownState {
+    last: Option<Instant>,
+    /// Records dropped since `last`
+    suppressed: u64,
+}
+
+/// Width the full level labels are padded to, the length of the longest label
+pub(crate) fn level_width(config: &LoggingConfig) -> usize {
+    Level::iter()
+        .map(|level| config.level_name(level))
+        .chain(config.target_label_names())
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(5)
+}
+
+/// `name` in the letter `case`
+fn recase(name: &str, case: LevelCase) -> String {
+    match case {
+        LevelCase::Upper => name.to_uppercase(),
+        LevelCase::Lower => name.to_lowercase(),
+        LevelCase::Title => {
+            let mut chars = name.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect()
+            })
+        }
+    }
+}
+
+/// The level field text for `name`, padded to `width` or shortened to its
+/// first character as configured
+pub(crate) fn level_text(config: &LoggingConfig, name: &str, width: usize) -> String {
+    let cased;
+    let name = match config.level_case() {
+        None => name,
+        Some(case) => {
+            cased = recase(name, case);
+            &cased
+        }
+    };
+    match config.level_label() {
+        LevelLabel::Full => format!("{name: <width$}"),
+        LevelLabel::Short => name.chars().next().unwrap_or(' ').to_string(),
+    }
+}
+
+/// Emit times of the written records, for `set_rate_status_interval`
+struct RateState {
+    /// When the previous status was due
+    last_status: Instant,
+    /// Records written per millisecond, oldest first, within the window
+    emits: VecDeque<(Instant, u64)>,
+}
+
+impl RateState {
+    fn new() -> Self {
+        Self {
+            last_status: Instant::now(),
+            emits: VecDeque::new(),
+        }
+    }
+
+    /// Note a record written at `now`, merging it into the entry of the same
+    /// millisecond, and forget the records older than `window`
+    fn note(&mut self, now: Instant, window: Duration) {
+        match self.emits.back_mut() {
+            Some((at, count)) if now.duration_since(*at) < Duration::from_millis(1) => *count += 1,
+            _ => self.emits.push_back((now, 1)),
+        }
+        self.expire(now, window);
+    }
+
+    fn expire(&mut self, now: Instant, window: Duration) {
+        while self
+            .emits
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > window)
+        {
+            self.emits.pop_front();
+        }
+    }
+
+    /// Records per second over `window` up to `now`
+    fn rate(&mut self, now: Instant, window: Duration) -> f64 {
+        self.expire(now, window);
+        let count: u64 = self.emits.iter().map(|(_, count)| count).sum();
+        let millis = window.as_millis().max(1);
+        count as f64 * 1000.0 / millis as f64
+    }
+}
+
+/// The status line of `set_rate_status_interval`
+fn rate_status(rate: f64, window: Duration) -> String {
+    format!(
+        "Logging {rate:.1} records/s over the last {}s",
+        window.as_secs_f64()
+    )
+}
+
+/// Formats records according to a `LoggingConfig`
+pub(crate) struct RecordFormatter {
+    config: LoggingConfig,
+    hostname: String,
+    time_format: String,
+    /// Format of the UTC half of dual timestamps
+    utc_time_format: Option<String>,
+    /// Width the full level labels are padded to
+    level_width: usize,
+    dedupe: Option<Mutex<DedupeState>>,
+    /// Records seen per level, for `set_sample_rate`
+    sample_counts: [AtomicU64; 5],
+    /// Whether the header line is still to be written
+    header_pending: AtomicBool,
+    /// When the previous human line was written, for `set_time_deltas`
+    last_line: Mutex<Option<Instant>>,
+    /// When the previous drop summary was due and the counters at that time.
+    /// Only set with `set_drop_summary_interval`, as `Instant::now` is not
+    /// available on every target.
+    last_drop_summary: Mutex<Option<(Instant, Stats)>>,
+    /// Only set with `set_rate_status_interval`, for the same reason
+    rate: Option<Mutex<RateState>>,
+    /// The widest `key=value` pair written per key, for `set_aligned_fields`
+    field_widths: Mutex<Vec<(String, usize)>>,
+    /// Hash of the last multi-line block, for `set_collapse_backtraces`
+    last_backtrace: Mutex<Option<u64>>,
+    /// Shared with the `TimedWriter` of the output, only set with
+    /// `set_write_latency_interval`
+    latency: Option<Arc<Mutex<LatencyState>>>,
+    /// Whether the output keeps the styling, for `set_level_border`
+    colored: bool,
+    /// Per level, for `set_level_cooldown`
+    cooldowns: [Mutex<CooldownState>; 5],
+    /// The lines of `recent_logs` the output writes into, if any
+    recent: Option<Arc<RecentLines>>,
+}
+
+impl RecordFormatter {
+    pub(crate) fn new(config: LoggingConfig) -> Self {
+        let hostname = match config.hostname() {
+            Some(hostname) => hostname.to_string(),
+            None => resolve_hostname(),
+        };
+
+        let time_format = resolve_time_format(&config);
+        let utc_time_format = config
+            .dual_timestamps()
+            .then(|| format!("{}Z", resolve_base_time_format(&config)));
+        let level_width = level_width(&config);
+        let header_pending = AtomicBool::new(
+            config.header()
+                && matches!(
+                    config.format(),
+                    LogFormat::Human | LogFormat::Csv | LogFormat::Columns
+                ),
+        );
+        let dedupe = (config.collapse_repeats() && config.format() == LogFormat::Human)
+            .then(|| Mutex::new(DedupeState::default()));
+
+        let last_drop_summary = Mutex::new(
+            config
+                .drop_summary_interval()
+                .map(|_| (Instant::now(), Stats::snapshot())),
+        );
+
+        if config.uptime() || config.elapsed_time() {
+            START.get_or_init(Instant::now);
+        }
+        let rate = config
+            .rate_status_interval()
+            .map(|_| Mutex::new(RateState::new()));
+        let latency = config
+            .write_latency_interval()
+            .map(|_| Arc::new(Mutex::new(LatencyState::new())));
+
+        Self {
+            hostname,
+            time_format,
+            utc_time_format,
+            level_width,
+            dedupe,
+            sample_counts: Default::default(),
+            header_pending,
+            last_line: Mutex::new(None),
+            last_drop_summary,
+            rate,
+            field_widths: Mutex::new(Vec::new()),
+            last_backtrace: Mutex::new(None),
+            latency,
+            colored: config.color().resolve(false),
+            cooldowns: Default::default(),
+            // binary frames can not be pasted into a bug report
+            recent: config
+                .recent_lines()
+                .filter(|_| config.format() != LogFormat::Binary),
+            config,
+        }
+    }
+
+    /// Set whether the output keeps the styling, as decided for its target
+    pub(crate) fn with_colored(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    /// Whether `record` passes the message content filters and the slow log
+    fn passes_filters(&self, record: &Record) -> bool {
+        if let Some(threshold) = self.config.slow_log_threshold() {
+            let slow = match fields::duration_ms(record.key_values()) {
+                Some(ms) => ms >= threshold.as_secs_f64() * 1000.0,
+                None => self.config.slow_log_keep_untimed(),
+            };
+            if !slow {
+                return false;
+            }
+        }
+        let drop_empty = self.config.empty_message() == EmptyMessage::Drop;
+        if !drop_empty
+            && self.config.deny_messages().is_empty()
+            && self.config.min_message_length() == 0
+        {
+            return true;
+        }
+        let message = record.args().to_string();
+        !(drop_empty && is_blank(&message))
+            && message.len() >= self.config.min_message_length()
+            && !self
+                .config
+                .deny_messages()
+                .iter()
+                .any(|denied| message.contains(denied.as_str()))
+    }
+
+    /// Whether `record` is written at all: it has to be within the level of the
+    /// current phase of `set_init_level`, pass the content filters and be kept
+    /// by the sampling rate of its level. Banners are always kept, and records
+    /// of `log_forced` skip the level and sampling checks.
+    pub(crate) fn keep(&self, record: &Record) -> bool {
+        if record.target() == BANNER_TARGET {
+            return true;
+        }
+        let forced = crate::force::forced();
+        // the logger was built for the more verbose of the init and steady levels
+        if !forced
+            && self.config.init_level().is_some()
+            && record.level() > self.config.phase_level_for(record.target())
+        {
+            return false;
+        }
+        if !self.passes_filters(record) {
+            if !secondary() {
+                stats::count_filtered();
+            }
+            return false;
+        }
+        if !forced && !self.sampled(record) {
+            if !secondary() {
+                stats::count_sampled_out();
+            }
+            return false;
+        }
+        true
+    }
+
+    /// The drop summary text if `set_drop_summary_interval` is set and due
+    fn due_drop_summary(&self) -> Option<String> {
+        let interval = self.config.drop_summary_interval()?;
+        let mut guard = self
+            .last_drop_summary
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let last = guard.as_mut()?;
+        if last.0.elapsed() < interval {
+            return None;
+        }
+        let stats = Stats::snapshot();
+        let summary = stats.dropped_since(&last.1, interval);
+        *last = (Instant::now(), stats);
+        summary
+    }
+
+    /// The rate status text if `set_rate_status_interval` is set and due
+    fn due_rate_status(&self) -> Option<String> {
+        let interval = self.config.rate_status_interval()?;
+        let mut state = self
+            .rate
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        if now.duration_since(state.last_status) < interval {
+            return None;
+        }
+        state.last_status = now;
+        let window = self.config.rate_status_window();
+        Some(rate_status(state.rate(now, window), window))
+    }
+
+    /// The write latency status text if `set_write_latency_interval` is set,
+    /// due and there were writes since the previous one
+    fn due_latency_status(&self) -> Option<String> {
+        let interval = self.config.write_latency_interval()?;
+        let mut state = self
+            .latency
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        if now.duration_since(state.last_status) < interval {
+            return None;
+        }
+        state.last_status = now;
+        state.status(interval)
+    }
+
+    /// The write times the output should note, if `set_write_latency_interval` is set
+    pub(crate) fn latency(&self) -> Option<Arc<Mutex<LatencyState>>> {
+        self.latency.clone()
+    }
+
+    /// Note a written record for `set_rate_status_interval`
+    fn note_emit(&self) {
+        if let Some(rate) = &self.rate {
+            rate.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .note(Instant::now(), self.config.rate_status_window());
+        }
+    }
+
+    /// Whether `record` is kept by the sampling rate of its level. Fatal
+    /// records are always kept.
+    fn sampled(&self, record: &Record) -> bool {
+        let rate = self.config.sample_rate(record.level());
+        if rate == 1 || record.target() == FATAL_TARGET {
+            return true;
+        }
+        let count = self.sample_counts[record.level() as usize - 1].fetch_add(1, Ordering::Relaxed);
+        count % rate == 0
+    }
+
+    /// The number of records of the level suppressed since the last one was
+    /// written, or `None` if `record` is suppressed by `set_level_cooldown`
+    pub(crate) fn cooldown(&self, record: &Record) -> Option<u64> {
+        let Some(cooldown) = self.config.level_cooldown(record.level()) else {
+            return Some(0);
+        };
+        if record.target() == FATAL_TARGET
+            || record.target() == BANNER_TARGET
+            || crate::force::forced()
+        {
+            return Some(0);
+        }
+        let now = Instant::now();
+        let mut state = self.cooldowns[record.level() as usize - 1]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state
+            .last
+            .is_some_and(|last| now.duration_since(last) < cooldown)
+        {
+            state.suppressed += 1;
+            if !secondary() {
+                stats::count_sampled_out();
+            }
+            return None;
+        }
+        state.last = Some(now);
+        Some(std::mem::take(&mut state.suppressed))
+    }
+
+    /// Write a single formatted record to `buf`. Nothing is written for records
+    /// that are filtered, sampled out or within the cooldown of their level.
+    pub(crate) fn write<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        let Some(recent) = &self.recent else {
+            return self.write_uncolored(buf, record);
+        };
+        let mut line = Vec::new();
+        self.write_uncolored(&mut line, record)?;
+        if !line.is_empty() {
+            recent.push(&strip_styles(&String::from_utf8_lossy(&line)));
+        }
+        buf.write_all(&line)
+    }
+
+    /// Write a record as `write` does, with the styling removed while
+    /// `set_color_enabled` has the color off
+    fn write_uncolored<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        if self.config.color_enabled() || self.config.format() == LogFormat::Binary {
+            return self.write_styled(buf, record);
+        }
+        let mut styled = Vec::new();
+        self.write_styled(&mut styled, record)?;
+        buf.write_all(strip_styles(&String::from_utf8_lossy(&styled)).as_bytes())
+    }
+
+    /// Write a record as `write` does, styled whatever `set_color_enabled` says
+    fn write_styled<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        if record.target() == FLUSH_TARGET {
+            return self.write_repeats(buf);
+        }
+        if secondary() {
+            return self.write_filtered(buf, record);
+        }
+        RECORD_SEQUENCE.with(|sequence| sequence.set(None));
+        if let Some(summary) = self.due_drop_summary() {
+            self.write_record(
+                buf,
+                &Record::builder()
+                    .args(format_args!("{summary}"))
+                    .level(Level::Info)
+                    .target("sdre_rust_logging")
+                    .build(),
+                false,
+            )?;
+        }
+        if let Some(status) = self.due_rate_status() {
+            self.write_record(
+                buf,
+                &Record::builder()
+                    .args(format_args!("{status}"))
+                    .level(Level::Info)
+                    .target("sdre_rust_logging")
+                    .build(),
+                false,
+            )?;
+        }
+        if let Some(status) = self.due_latency_status() {
+            self.write_record(
+                buf,
+                &Record::builder()
+                    .args(format_args!("{status}"))
+                    .level(Level::Info)
+                    .target("sdre_rust_logging")
+                    .build(),
+                false,
+            )?;
+        }
+        self.write_filtered(buf, record)
+    }
+
+    /// Write a record unless it is filtered, sampled out or within the
+    /// cooldown of its level, with the count of the records the cooldown
+    /// suppressed before it
+    fn write_filtered<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        if !self.keep(record) {
+            return Ok(());
+        }
+        let Some(suppressed) = self.cooldown(record) else {
+            return Ok(());
+        };
+        if suppressed > 0 {
+            let message = format!("{} ({suppressed} suppressed)", record.args());
+            return self.write_kept(
+                buf,
+                &Record::builder()
+                    .args(format_args!("{message}"))
+                    .metadata(record.metadata().clone())
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .key_values(record.key_values())
+                    .build(),
+            );
+        }
+        self.write_kept(buf, record)
+    }
+
+    /// Write a record that is kept, leaving out the `detail` key-value unless
+    /// `set_show_detail` is set
+    fn write_kept<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        if !secondary() {
+            self.note_emit();
+            if self.config.heartbeat_interval().is_some() {
+                heartbeat::note_emit();
+            }
+        }
+        if !self.config.show_detail()
+            && record
+                .key_values()
+                .get(Key::from_str(fields::DETAIL_KEY))
+                .is_some()
+        {
+            let shown = fields::Without {
+                source: record.key_values(),
+                key: fields::DETAIL_KEY,
+            };
+            return self.write_shown(
+                buf,
+                &Record::builder()
+                    .args(*record.args())
+                    .metadata(record.metadata().clone())
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .key_values(&shown)
+                    .build(),
+            );
+        }
+        self.write_shown(buf, record)
+    }
+
+    /// Write a kept record, collapsing its backtrace with `set_collapse_backtraces`
+    fn write_shown<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        if self.config.collapse_backtraces() {
+            if let Some(message) = self.collapse_backtrace(&record.args().to_string()) {
+                return self.write_record(
+                    buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .metadata(record.metadata().clone())
+                        .module_path(record.module_path())
+                        .file(record.file())
+                        .line(record.line())
+                        .key_values(record.key_values())
+                        .build(),
+                    self.dedupe.is_some(),
+                );
+            }
+        }
+        self.write_record(buf, record, self.dedupe.is_some())
+    }
+
+    /// The message with its backtrace collapsed, if it is a multi-line message
+    /// with the same lines after the first as the previous one.
+    fn collapse_backtrace(&self, message: &str) -> Option<String> {
+        let (first, block) = message.split_once('\n')?;
+        let mut hasher = DefaultHasher::new();
+        block.hash(&mut hasher);
+        let hash = hasher.finish();
+        let mut last = self
+            .last_backtrace
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *last == Some(hash) {
+            return Some(format!("{first}\n(backtrace identical to previous)"));
+        }
+        *last = Some(hash);
+        None
+    }
+
+    /// Write a record that passed the filters, collapsing repeats if `dedupe`
+    fn write_record<W: Write + ?Sized>(
+        &self,
+        buf: &mut W,
+        record: &Record,
+        dedupe: bool,
+    ) -> io::Result<()> {
+        if record.target() == BANNER_TARGET
+            && matches!(
+                self.config.format(),
+                LogFormat::Human | LogFormat::CommonLog
+            )
+        {
+            let style = self.config.current_theme().timestamp;
+            return writeln!(buf, "{style}{}{style:#}", record.args());
+        }
+        if self.header_pending.swap(false, Ordering::Relaxed) {
+            buf.write_all(self.header().as_bytes())?;
+        }
+        if !secondary() {
+            stats::count_record(record.level());
+        }
+        // only the formats writing the number take one
+        let sequence = (self.config.sequence_numbers()
+            && !matches!(
+                self.config.format(),
+                LogFormat::Columns | LogFormat::LegacyV1 | LogFormat::Binary
+            ))
+        .then(next_sequence);
+
+        match self.config.format() {
+            LogFormat::Human if dedupe => self.write_deduped(buf, record, sequence),
+            LogFormat::Human => self.write_human(buf, record, sequence),
+            LogFormat::Json if self.config.json_pretty() => {
+                let mut line = Vec::new();
+                self.write_json(&mut line, record, sequence)?;
+                buf.write_all(&pretty_json(&line))
+            }
+            LogFormat::Json => self.write_json(buf, record, sequence),
+            LogFormat::Csv => self.write_csv(buf, record, sequence),
+            LogFormat::CommonLog => self.write_common_log(buf, record, sequence),
+            LogFormat::Columns => self.write_columns(buf, record),
+            LogFormat::LegacyV1 => write_legacy_v1(buf, record, self.config.now()),
+            LogFormat::Binary => {
+                binary::write_frame(buf, record, self.config.now(), &self.render_message(record))
+            }
+        }
+    }
+
+    pub(crate) fn render_message(&self, record: &Record) -> String {
+        let mut message = record.args().to_string();
+        if is_blank(&message) && self.config.empty_message() == EmptyMessage::Placeholder {
+            return String::from("<empty>");
+        }
+        if self.config.strip_message_ansi() {
+            message = strip_ansi_sequences(&message);
+        }
+        // JSON strings escape control characters themselves
+        if self.config.escape_control_characters() && self.config.format() != LogFormat::Json {
+            message = escape_control_characters(&message);
+        }
+        // after the escaping, which lengthens the message
+        if let Some(max_len) = self.config.max_message_length() {
+            truncate_message(&mut message, max_len);
+        }
+        message
+    }
+
+    /// Whether the message has to be rendered to a `String` before writing
+    fn needs_rendering(&self) -> bool {
+        self.config.max_message_length().is_some()
+            || self.config.escape_control_characters()
+            || self.config.strip_message_ansi()
+            || self.config.empty_message() == EmptyMessage::Placeholder
+            || !self.config.highlights().is_empty()
+    }
+
+    /// Write a human line unless it repeats the previous one. A run of repeats
+    /// is summarized by writing its last line with a `(xN)` count, where N is
+    /// the total number of occurrences, once a different line is logged or
+    /// the `LoggingGuard` is dropped.
+    fn write_deduped<W: Write + ?Sized>(
+        &self,
+        buf: &mut W,
+        record: &Record,
+        sequence: Option<u64>,
+    ) -> io::Result<()> {
+        let key = format!(
+            "{}\0{}\0{}{}",
+            level_name(record),
+            record.target(),
+            self.render_message(record),
+            fields::human_fields(record.key_values())
+        );
+        let mut line = Vec::new();
+        self.write_human(&mut line, record, sequence)?;
+
+        let Some(dedupe) = &self.dedupe else {
+            return buf.write_all(&line);
+        };
+        let mut state = dedupe
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.key == key {
+            if !secondary() {
+                stats::count_collapsed();
+            }
+            state.repeats += 1;
+            state.pending = line;
+            return Ok(());
+        }
+
+        write_pending(buf, &mut state)?;
+        state.key = key;
+        buf.write_all(&line)
+    }
+
+    /// Write the count of the run of repeats held back by `write_deduped`, if
+    /// any, so a later identical line starts a new run
+    fn write_repeats<W: Write + ?Sized>(&self, buf: &mut W) -> io::Result<()> {
+        let Some(dedupe) = &self.dedupe else {
+            return Ok(());
+        };
+        let mut state = dedupe
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.repeats > 0 {
+            state.key.clear();
+        }
+        write_pending(buf, &mut state)
+    }
+
+    /// Join the prefix fields of a human line, ready for the message to follow
+    fn join_fields<'a>(&self, fields: &'a [&'a dyn fmt::Display]) -> Prefix<'a> {
+        Prefix {
+            fields,
+            brackets: self.config.brackets(),
+        }
+    }
+
+    /// Time since the previous human line, zero for the first line
+    fn time_delta(&self) -> Duration {
+        let now = Instant::now();
+        let mut last_line = self
+            .last_line
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let delta = last_line.map_or(Duration::ZERO, |last| now.duration_since(last));
+        *last_line = Some(now);
+        delta
+    }
+
+    /// The header line naming the fields of a human or CSV line
+    fn header(&self) -> String {
+        if self.config.format() == LogFormat::Csv {
+            let sequence = if self.config.sequence_numbers() {
+                "sequence,"
+            } else {
+                ""
+            };
+            return format!("{sequence}timestamp,level,target,message,fields\n");
+        }
+        if self.config.format() == LogFormat::Columns {
+            let widths = self.config.column_widths();
+            return format!(
+                "{} {} {} message\n",
+                fit_column("level", widths.level),
+                fit_column("timestamp", widths.timestamp),
+                fit_column("target", widths.target),
+            );
+        }
+
+        let mut fields: Vec<&dyn fmt::Display> = Vec::with_capacity(5);
+        if self.config.sequence_numbers() {
+            fields.push(&"sequence");
+        }
+        if !self.config.show_level() {
+            fields.push(&"timestamp");
+        } else if self.config.timestamp_first() {
+            fields.extend([&"timestamp" as &dyn fmt::Display, &"level"]);
+        } else {
+            fields.extend([&"level" as &dyn fmt::Display, &"timestamp"]);
+        }
+        if self.config.time_deltas() {
+            fields.push(&"delta");
+        }
+        if self.config.uptime() {
+            fields.push(&"uptime");
+        }
+        format!("{}message\n", self.join_fields(&fields))
+    }
+
+    fn write_human<W: Write + ?Sized>(
+        &self,
+        buf: &mut W,
+        record: &Record,
+        sequence: Option<u64>,
+    ) -> io::Result<()> {
+        let fatal = record.target() == FATAL_TARGET;
+        // a fatal line is styled as a whole, so the fields must not reset the style part way
+        let theme = self.config.current_theme();
+        let target_label = self.config.target_label(record.target());
+        let (line_style, time_style, level_style) = if fatal {
+            (theme.error, Style::new(), Style::new())
+        } else if record.level() > self.config.color_min_level() {
+            (Style::new(), Style::new(), Style::new())
+        } else if let Some((_, style)) = target_label {
+            (Style::new(), theme.timestamp, style)
+        } else {
+            (Style::new(), theme.timestamp, theme.level(record.level()))
+        };
+        let level_name = match target_label {
+            _ if fatal => "FATAL",
+            Some((label, _)) => label,
+            None => self.config.level_name(record.level()),
+        };
+
+        let level = format!(
+            "{level_style}{}{level_style:#}",
+            level_text(&self.config, level_name, self.level_width)
+        );
+        let time = Timestamp {
+            now: self.config.now(),
+            format: &self.time_format,
+            utc_format: self.utc_time_format.as_deref(),
+            rfc3339: self.rfc3339(record.level()),
+            elapsed: self.config.elapsed_time().then(uptime),
+            epoch: self.config.epoch_timestamps().map(|width| {
+                (
+                    width,
+                    self.config.level_time_precision(record.level()).digits(),
+                )
+            }),
+            utc: self.config.utc_timestamps() && !self.config.dual_timestamps(),
+            style: time_style,
+        };
+        let delta = self
+            .config
+            .time_deltas()
+            .then(|| format!("+{}ms", self.time_delta().as_millis()));
+        let uptime = self
+            .config
+            .uptime()
+            .then(|| format!("up {:.3}s", uptime().as_secs_f64()));
+
+        let mut fields: Vec<&dyn fmt::Display> = Vec::with_capacity(5);
+        if let Some(sequence) = &sequence {
+            fields.push(sequence);
+        }
+        if !self.config.show_level() {
+            fields.push(&time);
+        } else if self.config.timestamp_first() {
+            fields.extend([&time as &dyn fmt::Display, &level]);
+        } else {
+            fields.extend([&level as &dyn fmt::Display, &time]);
+        }
+        if let Some(delta) = &delta {
+            fields.push(delta);
+        }
+        if let Some(uptime) = &uptime {
+            fields.push(uptime);
+        }
+        let prefix = self.join_fields(&fields);
+        let border = self.level_border(record, fatal);
+
+        let mut fields = self.human_key_values(record);
+        if self.config.field_count() {
+            fields.push_str(&format!(" fields={}", record.key_values().count()));
+        }
+        if let Some(column) = self.config.aligned_fields() {
+            let width = self.render_message(record).chars().count();
+            if !fields.is_empty() && width < column {
+                fields.insert_str(0, &" ".repeat(column - width));
+            }
+        }
+
+        if let Some(column) = self.config.wrap_column() {
+            let prefix = prefix.to_string();
+            let indent = visible_width(&prefix).min(column / 2);
+            let text = format!("{}{fields}", self.render_message(record));
+            let border_width = visible_width(&border);
+            return writeln!(
+                buf,
+                "{border}{line_style}{prefix}{}{line_style:#}",
+                wrap_text(&text, column, border_width + visible_width(&prefix), indent)
+            );
+        }
+
+        if self.needs_rendering() {
+            let mut message = self.render_message(record);
+            if !self.config.highlights().is_empty() {
+                message = highlight_matches(&message, self.config.highlights(), line_style);
+            }
+            writeln!(
+                buf,
+                "{border}{line_style}{}{}{}{line_style:#}",
+                prefix, message, fields
+            )
+        } else {
+            writeln!(
+                buf,
+                "{border}{line_style}{}{}{}{line_style:#}",
+                prefix,
+                record.args(),
+                fields
+            )
+        }
+    }
+
+    /// The glyph of `set_level_border` and a space in the level's color, or
+    /// nothing if it is not set or the output is not colored
+    fn level_border(&self, record: &Record, fatal: bool) -> String {
+        match self.config.level_border() {
+            Some(glyph) if self.colored && self.config.color_enabled() => {
+                let theme = self.config.current_theme();
+                let style = if fatal {
+                    theme.error
+                } else {
+                    theme.level(record.level())
+                };
+                format!("{style}{glyph}{style:#} ")
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// The ` key=value` pairs of a human line, with `set_shown_fields` and
+    /// `set_aligned_fields` applied
+    fn human_key_values(&self, record: &Record) -> String {
+        let shown = self.config.shown_fields();
+        let aligned = self.config.aligned_fields().is_some();
+        if shown.is_empty() && !aligned {
+            return fields::human_fields(record.key_values());
+        }
+
+        let mut pairs = fields::human_pairs(record.key_values());
+        if !shown.is_empty() {
+            pairs = shown
+                .iter()
+                .filter_map(|key| pairs.iter().find(|(known, _)| known == key).cloned())
+                .collect();
+        }
+        let mut widths = self
+            .field_widths
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut text = String::new();
+        for (index, (key, value)) in pairs.iter().enumerate() {
+            let pair = format!("{key}={value}");
+            // the last pair is not padded, so lines do not end in spaces
+            if !aligned || index + 1 == pairs.len() {
+                text.push(' ');
+                text.push_str(&pair);
+                continue;
+            }
+            let len = pair.chars().count();
+            let width = match widths.iter_mut().find(|(known, _)| known == key) {
+                Some((_, width)) => {
+                    *width = (*width).max(len);
+                    *width
+                }
+                None => {
+                    widths.push((key.clone(), len));
+                    len
+                }
+            };
+            text.push_str(&format!(" {pair:<width$}"));
+        }
+        text
+    }
+
+    fn seconds_format(&self, level: Level) -> SecondsFormat {
+        self.config.level_time_precision(level).seconds_format()
+    }
+
+    /// The precision of human timestamps at `level` if `set_rfc3339_timestamps` is set
+    fn rfc3339(&self, level: Level) -> Option<SecondsFormat> {
+        self.config
+            .rfc3339_timestamps()
+            .then(|| self.seconds_format(level))
+    }
+
+    fn write_csv<W: Write + ?Sized>(
+        &self,
+        buf: &mut W,
+        record: &Record,
+        sequence: Option<u64>,
+    ) -> io::Result<()> {
+        if let Some(sequence) = sequence {
+            write!(buf, "{sequence},")?;
+        }
+        let timestamp = self
+            .config
+            .now()
+            .to_rfc3339_opts(self.seconds_format(record.level()), true);
+        write!(buf, "{timestamp},{},", level_name(record))?;
+        write_csv_field(buf, record.target())?;
+        buf.write_all(b",")?;
+        write_csv_field(buf, &self.render_message(record))?;
+        buf.write_all(b",")?;
+        write_csv_field(buf, fields::human_fields(record.key_values()).trim_start())?;
+        buf.write_all(b"\n")
+    }
+
+    /// Write `record` as fixed width columns
+    fn write_columns<W: Write + ?Sized>(&self, buf: &mut W, record: &Record) -> io::Result<()> {
+        let widths = self.config.column_widths();
+        let level = match record.target() {
+            FATAL_TARGET => "FATAL",
+            _ => self.config.level_name(record.level()),
+        };
+        let now = wall_time(self.config.now(), self.config.utc_timestamps());
+        let time = match self.rfc3339(record.level()) {
+            Some(seconds) => now.to_rfc3339_opts(seconds, true),
+            None => now.format(&self.time_format).to_string(),
+        };
+        let message = format!(
+            "{}{}",
+            self.render_message(record),
+            fields::human_fields(record.key_values())
+        );
+        let message = match widths.message {
+            Some(width) => fit_column(&message, width),
+            None => message,
+        };
+        writeln!(
+            buf,
+            "{} {} {} {message}",
+            fit_column(level, widths.level),
+            fit_column(&time, widths.timestamp),
+            fit_column(record.target(), widths.target),
+        )
+    }
+
+    /// Write `record` in Common Log Format, or as a human line if it lacks the
+    /// request key-values
+    fn write_common_log<W: Write + ?Sized>(
+        &self,
+        buf: &mut W,
+        record: &Record,
+        sequence: Option<u64>,
+    ) -> io::Result<()> {
+        let source = record.key_values();
+        let field = |key: &str| {
+            source
+                .get(Key::from_str(key))
+                .map(|value| value.to_string())
+        };
+        let (Some(method), Some(path), Some(status), Some(bytes)) = (
+            field("method"),
+            field("path"),
+            field("status"),
+            field("bytes"),
+        ) else {
+            return self.write_human(buf, record, sequence);
+        };
+        // an empty value has no meaning in the format, it is a dash instead
+        let dash = |value: Option<String>| {
+            value
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| String::from("-"))
+        };
+        let quoted = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+        let bytes = if bytes == "0" {
+            String::from("-")
+        } else {
+            bytes
+        };
+        let protocol = field("protocol").unwrap_or_else(|| String::from("HTTP/1.1"));
+
+        write!(
+            buf,
+            "{} - {} [{}] \"{} {} {}\" {status} {bytes}",
+            dash(field("remote")),
+            dash(field("user")),
+            wall_time(self.config.now(), self.config.utc_timestamps())
+                .format("%d/%b/%Y:%H:%M:%S %z"),
+            quoted(&method),
+            quoted(&path),
+            quoted(&protocol),
+        )?;
+        let (referer, user_agent) = (field("referer"), field("user_agent"));
+        if referer.is_some() || user_agent.is_some() {
+            write!(
+                buf,
+                " \"{}\" \"{}\"",
+                quoted(&dash(referer)),
+                quoted(&dash(user_agent))
+            )?;
+        }
+        buf.write_all(b"\n")
+    }
+
+    fn write_json<W: Write + ?Sized>(
+        &self,
+        buf: &mut W,
+        record: &Record,
+        sequence: Option<u64>,
+    ) -> io::Result<()> {
+        buf.write_all(b"{")?;
+        if self.config.json_timestamp() {
+            let now = self.config.now();
+            buf.write_all(b"\"timestamp\":")?;
+            let seconds = self.seconds_format(record.level());
+            write_json_string(buf, &now.to_rfc3339_opts(seconds, true))?;
+            if self.config.dual_timestamps() {
+                buf.write_all(b",\"local_timestamp\":")?;
+                write_json_string(
+                    buf,
+                    &now.with_timezone(&Local).to_rfc3339_opts(seconds, false),
+                )?;
+            }
+            buf.write_all(b",")?;
+        }
+        if let Some(sequence) = sequence {
+            write!(buf, "\"sequence\":{sequence},")?;
+        }
+        if self.config.uptime() {
+            write!(buf, "\"uptime\":{:.3},", uptime().as_secs_f64())?;
+        }
+        buf.write_all(b"\"host\":")?;
+        write_json_string(buf, &self.hostname)?;
+        buf.write_all(b",\"level\":")?;
+        write_json_string(buf, level_name(record))?;
+        buf.write_all(b",\"target\":")?;
+        write_json_string(buf, record.target())?;
+        buf.write_all(b",\"message\":")?;
+        write_json_string(buf, &self.render_message(record))?;
+        buf.write_all(&fields::json_fields(record.key_values()))?;
+        if self.config.field_count() {
+            write!(buf, ",\"fields\":{}", record.key_values().count())?;
+        }
+        buf.write_all(b"}\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ColorPreference, ColorTheme, ColumnWidths, TimePrecision};
+    /// Format a single record with `config` and return the output
+    fn render(config: LoggingConfig, level: Level, target: &str, message: &str) -> String {
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config)
+            .write(
+                &mut buf,
+                &Record::builder()
+                    .args(format_args!("{message}"))
+                    .level(level)
+                    .target(target)
+                    .build(),
+            )
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_timezone_suffix() {
+        let now = Local::now();
+        let plain = now
+            .format(&resolve_time_format(&LoggingConfig::new()))
+            .to_string();
+        let offset = now
+            .format(&resolve_time_format(
+                &LoggingConfig::new().set_timezone_suffix(TimezoneSuffix::Offset),
+            ))
+            .to_string();
+        assert_eq!(plain.len(), "2021-08-22T15:49:01".len());
+        assert_eq!(
+            resolve_time_format(&LoggingConfig::new().set_time_format("%Q")),
+            DEFAULT_TIME_FORMAT
+        );
+        assert_eq!(offset.len(), "2021-08-22T15:49:01-07:00".len());
+        assert!(offset.ends_with(|c: char| c.is_ascii_digit()));
+        assert_eq!(offset.as_bytes()[offset.len() - 3], b':');
+    }
+
+    #[test]
+    fn test_date_time_separator() {
+        let config = LoggingConfig::new().set_date_time_separator(' ');
+        assert_eq!(resolve_time_format(&config), "%Y-%m-%d %H:%M:%S");
+        let config = LoggingConfig::new().set_date_time_separator('%');
+        assert_eq!(resolve_time_format(&config), "%Y-%m-%d%%%H:%M:%S");
+        let config = config.set_time_format("%H:%M");
+        assert_eq!(resolve_time_format(&config), "%H:%M");
+        assert_eq!(
+            resolve_time_format(&LoggingConfig::new()),
+            DEFAULT_TIME_FORMAT
+        );
+    }
+
+    #[test]
+    fn test_dual_timestamps() {
+        let config = LoggingConfig::new().set_dual_timestamps(true);
+        let output = strip_styles(&render(config, Level::Info, "app", "hello"));
+        let time = output
+            .strip_prefix("[INFO ][")
+            .unwrap()
+            .split_once(']')
+            .unwrap()
+            .0;
+        let (local, utc) = time.split_once(" / ").unwrap();
+        assert_eq!(local.len(), "2021-08-22T15:49:01-07:00".len());
+        assert!(chrono::DateTime::parse_from_rfc3339(local).is_ok());
+        assert_eq!(utc.len(), "2021-08-22T22:49:01Z".len());
+        assert!(chrono::DateTime::parse_from_rfc3339(utc).is_ok());
+
+        let config = LoggingConfig::new()
+            .set_dual_timestamps(true)
+            .set_format(LogFormat::Json);
+        let output = render(config, Level::Info, "app", "hello");
+        assert!(!output.contains(" / "));
+        let (_, local) = output.split_once(",\"local_timestamp\":\"").unwrap();
+        let (local, _) = local.split_once('"').unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(local).is_ok());
+    }
+
+    #[test]
+    fn test_max_message_length() {
+        let config = LoggingConfig::new().set_max_message_length(Some(16));
+        let message = "0A".repeat(1024 * 1024);
+        let output = render(config, Level::Info, "app", &message);
+        assert!(output.ends_with("]0A0A0A0A0A0A0A0A… [2097136 bytes truncated]\n"));
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        let mut message = String::from("ééé");
+        truncate_message(&mut message, 3);
+        assert_eq!(message, "é… [4 bytes truncated]");
+
+        let mut short = String::from("short");
+        truncate_message(&mut short, 16);
+        assert_eq!(short, "short");
+    }
+
+    #[test]
+    fn test_truncate_escape_sequence() {
+        let mut split = String::from("ab\x1b[31mred");
+        truncate_message(&mut split, 5);
+        assert_eq!(split, "ab… [8 bytes truncated]");
+
+        let mut kept = String::from("\x1b[1mbold text");
+        truncate_message(&mut kept, 6);
+        assert_eq!(kept, "\x1b[1mbo… [7 bytes truncated]");
+    }
+
+    #[test]
+    fn test_truncate_after_escaping() {
+        let config = LoggingConfig::new()
+            .set_max_message_length(Some(8))
+            .set_escape_control_characters(true);
+        let output = render(config, Level::Info, "app", &"\n".repeat(10));
+        assert!(output.ends_with("]\\n\\n\\n\\n… [12 bytes truncated]\n"));
+    }
+
+    #[test]
+    fn test_json_format() {
+        let config = LoggingConfig::new()
+            .set_format(LogFormat::Json)
+            .set_hostname(Some(String::from("receiver")));
+        let output = render(config, Level::Warn, "acars_router", "say \"hi\"\\\n");
+        let (timestamp, rest) = output
+            .strip_prefix("{\"timestamp\":\"")
+            .unwrap()
+            .split_once('"')
+            .unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        assert!(timestamp.ends_with('Z'));
+        assert_eq!(
+            rest,
+            ",\"host\":\"receiver\",\"level\":\"WARN\",\"target\":\"acars_router\",\"message\":\"say \\\"hi\\\"\\\\\\n\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_rfc3339_timestamps() {
+        let config = LoggingConfig::new()
+            .set_timezone_suffix(TimezoneSuffix::None)
+            .set_rfc3339_timestamps(true);
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config.clone())
+            .write_human(
+                &mut buf,
+                &Record::builder().args(format_args!("message")).build(),
+                None,
+            )
+            .unwrap();
+        let line = strip_styles(&String::from_utf8(buf).unwrap());
+        let timestamp = line
+            .strip_prefix("[INFO ][")
+            .and_then(|rest| rest.strip_suffix("]message\n"))
+            .unwrap();
+        assert!(DateTime::parse_from_rfc3339(timestamp).is_ok());
+        // three digits of `TimePrecision::Millis`, then the offset or `Z`
+        let fraction = timestamp.split_once('.').unwrap().1;
+        assert!(fraction[..3].bytes().all(|digit| digit.is_ascii_digit()));
+        assert!(matches!(fraction.as_bytes()[3], b'+' | b'-' | b'Z'));
+
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config.set_dual_timestamps(true))
+            .write_human(
+                &mut buf,
+                &Record::builder().args(format_args!("message")).build(),
+                None,
+            )
+            .unwrap();
+        let line = strip_styles(&String::from_utf8(buf).unwrap());
+        let (local, utc) = line[8..line.len() - 9].split_once(" / ").unwrap();
+        assert!(DateTime::parse_from_rfc3339(local).is_ok());
+        assert!(utc.ends_with('Z'));
+        assert_eq!(
+            DateTime::parse_from_rfc3339(local).unwrap(),
+            DateTime::parse_from_rfc3339(utc).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_time_precision() {
+        let time = chrono::DateTime::parse_from_rfc3339("2021-08-22T22:49:01.123456789Z").unwrap();
+        let expected = [
+            (TimePrecision::Seconds, "2021-08-22T22:49:01Z"),
+            (TimePrecision::Millis, "2021-08-22T22:49:01.123Z"),
+            (TimePrecision::Micros, "2021-08-22T22:49:01.123456Z"),
+            (TimePrecision::Nanos, "2021-08-22T22:49:01.123456789Z"),
+        ];
+        for (precision, formatted) in expected {
+            assert_eq!(
+                time.to_rfc3339_opts(precision.seconds_format(), true),
+                formatted
+            );
+
+            let config = LoggingConfig::new()
+                .set_format(LogFormat::Json)
+                .set_time_precision(precision);
+            let output = render(config, Level::Info, "app", "x");
+            let timestamp = output
+                .strip_prefix("{\"timestamp\":\"")
+                .unwrap()
+                .split_once('"')
+                .unwrap()
+                .0;
+            assert_eq!(timestamp.len(), formatted.len());
+        }
+    }
+
+    #[test]
+    fn test_level_time_precision() {
+        use chrono::TimeZone;
+
+        let config = LoggingConfig::new()
+            .set_format(LogFormat::Csv)
+            .set_clock(|| Utc.with_ymd_and_hms(2021, 8, 22, 22, 49, 1).unwrap())
+            .set_level_time_precision(Level::Info, TimePrecision::Seconds)
+            .set_level_time_precision(Level::Error, TimePrecision::Micros);
+        assert_eq!(
+            config.level_time_precision(Level::Warn),
+            TimePrecision::Millis
+        );
+        let timestamp = |level| {
+            render(config.clone(), level, "app", "x")
+                .split_once(',')
+                .unwrap()
+                .0
+                .to_string()
+        };
+        assert_eq!(timestamp(Level::Info), "2021-08-22T22:49:01Z");
+        assert_eq!(timestamp(Level::Warn), "2021-08-22T22:49:01.000Z");
+        assert_eq!(timestamp(Level::Error), "2021-08-22T22:49:01.000000Z");
+    }
+
+    #[test]
+    fn test_json_pretty() {
+        assert_eq!(
+            String::from_utf8(pretty_json(b"{\"a\":\"x,{\\\":}\",\"b\":1}\n")).unwrap(),
+            "{\n  \"a\": \"x,{\\\":}\",\n  \"b\": 1\n}\n"
+        );
+        assert_eq!(
+            String::from_utf8(pretty_json(
+                b"{\"errors\":[\"lost\",{\"code\":5}],\"empty\":{},\"none\":[]}\n"
+            ))
+            .unwrap(),
+            [
+                "{",
+                "  \"errors\": [",
+                "    \"lost\",",
+                "    {",
+                "      \"code\": 5",
+                "    }",
+                "  ],",
+                "  \"empty\": {},",
+                "  \"none\": []",
+                "}\n",
+            ]
+            .join("\n")
+        );
+
+        let config = LoggingConfig::new()
+            .set_format(LogFormat::Json)
+            .set_json_pretty(true)
+            .set_hostname(Some(String::from("receiver")));
+        let output = render(config, Level::Info, "app", "hi");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[1].starts_with("  \"timestamp\": \""));
+        assert_eq!(
+            &lines[2..],
+            [
+                "  \"host\": \"receiver\",",
+                "  \"level\": \"INFO\",",
+                "  \"target\": \"app\",",
+                "  \"message\": \"hi\"",
+                "}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_message() {
+        let keep = strip_styles(&render(LoggingConfig::new(), Level::Info, "app", ""));
+        assert!(keep.starts_with("[INFO ]["));
+        assert!(keep.ends_with("]\n"));
+
+        let config = LoggingConfig::new().set_empty_message(EmptyMessage::Drop);
+        assert_eq!(render(config.clone(), Level::Info, "app", ""), "");
+        assert!(strip_styles(&render(config, Level::Info, "app", "x")).ends_with("]x\n"));
+
+        let config = LoggingConfig::new().set_empty_message(EmptyMessage::Placeholder);
+        let human = strip_styles(&render(config.clone(), Level::Info, "app", ""));
+        assert!(human.ends_with("]<empty>\n"));
+        let json = render(config.set_format(LogFormat::Json), Level::Info, "app", "");
+        assert!(json.contains("\"message\":\"<empty>\""));
+    }
+
+    #[test]
+    fn test_whitespace_message() {
+        let keep = strip_styles(&render(LoggingConfig::new(), Level::Info, "app", "   "));
+        assert!(keep.ends_with("]   \n"));
+
+        let config = LoggingConfig::new().set_empty_message(EmptyMessage::Drop);
+        for message in ["   ", "\t\n", "\u{3000}"] {
+            assert_eq!(render(config.clone(), Level::Info, "app", message), "");
+        }
+        assert!(strip_styles(&render(config, Level::Info, "app", " x ")).ends_with("] x \n"));
+
+        let config = LoggingConfig::new().set_empty_message(EmptyMessage::Placeholder);
+        let human = strip_styles(&render(config, Level::Info, "app", " \r\n"));
+        assert!(human.ends_with("]<empty>\n"));
+    }
+
+    #[test]
+    fn test_banners() {
+        let config = LoggingConfig::new()
+            .set_header(true)
+            .set_deny_messages(&["==="]);
+        let formatter = RecordFormatter::new(config.clone());
+        let mut buf: Vec<u8> = Vec::new();
+        for (target, message) in [(BANNER_TARGET, "=== start ==="), ("app", "running")] {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(Level::Info)
+                        .target(target)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        let style = config.theme().timestamp;
+        assert_eq!(lines[0], format!("{style}=== start ==={style:#}"));
+        assert_eq!(lines[1], "[level][timestamp]message");
+        assert!(strip_styles(lines[2]).ends_with("]running"));
+
+        let json = render(
+            config.set_format(LogFormat::Json),
+            Level::Info,
+            BANNER_TARGET,
+            "=== start ===",
+        );
+        assert!(json.contains("\"message\":\"=== start ===\""));
+    }
+
+    #[test]
+    fn test_json_timestamp() {
+        let config = LoggingConfig::new()
+            .set_format(LogFormat::Json)
+            .set_hostname(Some(String::from("receiver")));
+        let stamped = render(config.clone(), Level::Info, "app", "hi");
+        assert!(stamped.starts_with("{\"timestamp\":\""));
+
+        let config = config.set_json_timestamp(false).set_dual_timestamps(true);
+        assert_eq!(
+            render(config.clone(), Level::Info, "app", "hi"),
+            "{\"host\":\"receiver\",\"level\":\"INFO\",\"target\":\"app\",\"message\":\"hi\"}\n"
+        );
+        let sequenced = render(config.set_sequence_numbers(true), Level::Info, "app", "hi");
+        assert!(sequenced.starts_with("{\"sequence\":"));
+        assert!(!sequenced.contains("timestamp"));
+    }
+
+    #[test]
+    fn test_escape_control_characters() {
+        let message = "null\x00 escape\x1b[31m newline\nnext";
+
+        let config = LoggingConfig::new().set_escape_control_characters(true);
+        let output = render(config, Level::Info, "app", message);
+        assert!(output.ends_with("]null\\x00 escape\\x1b[31m newline\\nnext\n"));
+        assert_eq!(output.matches('\n').count(), 1);
+
+        let json = "\"message\":\"null\\u0000 escape\\u001b[31m newline\\nnext\"}\n";
+        let config = LoggingConfig::new().set_format(LogFormat::Json);
+        let output = render(config.clone(), Level::Info, "app", message);
+        assert!(output.ends_with(json));
+        let output = render(
+            config.set_escape_control_characters(true),
+            Level::Info,
+            "app",
+            message,
+        );
+        assert!(output.ends_with(json));
+    }
+
+    #[test]
+    fn test_strip_message_ansi() {
+        let message = "\x1b[1;31mred\x1b[0m \x1b]0;title\x07\x1b]8;;http://x\x1b\\link\x1b7 \x1b[2Kcleared\tend\x1b[";
+
+        let config = LoggingConfig::new().set_strip_message_ansi(true);
+        let output = render(config.clone(), Level::Info, "app", message);
+        assert!(output.ends_with("]red link cleared\tend\n"));
+        let output = render(
+            config.clone().set_escape_control_characters(true),
+            Level::Info,
+            "app",
+            message,
+        );
+        assert!(output.ends_with("]red link cleared\\tend\n"));
+        let output = render(
+            config.set_format(LogFormat::Json),
+            Level::Info,
+            "app",
+            message,
+        );
+        assert!(output.ends_with("\"message\":\"red link cleared\\tend\"}\n"));
+
+        // the crate's own styling is untouched
+        let output = render(
+            LoggingConfig::new().set_strip_message_ansi(true),
+            Level::Info,
+            "app",
+            "x",
+        );
+        assert!(output.starts_with("[\x1b["));
+    }
+
+    #[test]
+    fn test_compact_preset() {
+        let output = strip_styles(&render(
+            LoggingConfig::compact(),
+            Level::Info,
+            "app",
+            "message",
+        ));
+        let (time, rest) = output.split_once(' ').unwrap();
+        assert_eq!(time.len(), "15:49:01".len());
+        assert_eq!(time.matches(':').count(), 2);
+        assert_eq!(rest, "I message\n");
+
+        let output = strip_styles(&render(LoggingConfig::new(), Level::Warn, "app", "message"));
+        assert!(output.starts_with("[WARN ]["));
+        assert!(output.ends_with("]message\n"));
+    }
+
+    #[test]
+    fn test_fatal_record() {
+        let output = render(
+            LoggingConfig::new(),
+            Level::Error,
+            FATAL_TARGET,
+            "giving up",
+        );
+        let red = ColorTheme::default().error;
+        assert!(output.starts_with(&format!("{red}[FATAL][")));
+        assert!(output.ends_with(&format!("]giving up{red:#}\n")));
+        // the fields inside the line are unstyled so the red is not reset early
+        assert_eq!(output.matches("\x1b[0m").count(), 1);
+
+        let config = LoggingConfig::new().set_format(LogFormat::Json);
+        let output = render(config, Level::Error, FATAL_TARGET, "giving up");
+        assert!(output.contains("\"level\":\"FATAL\""));
+    }
+
+    #[test]
+    fn test_uncolored_field() {
+        let theme = ColorTheme::default();
+        let colored = render(LoggingConfig::new(), Level::Info, "app", "message");
+        assert!(colored.starts_with(&format!(
+            "[{}INFO {:#}][{}",
+            theme.info, theme.info, theme.timestamp
+        )));
+
+        let plain_time = ColorTheme {
+            timestamp: Style::new(),
+            ..ColorTheme::default()
+        };
+        let output = render(
+            LoggingConfig::new().set_theme(plain_time),
+            Level::Info,
+            "app",
+            "message",
+        );
+        assert!(output.starts_with(&format!("[{}INFO {:#}][2", theme.info, theme.info)));
+        assert_eq!(output.matches("\x1b[0m").count(), 1);
+    }
+
+    #[test]
+    fn test_binary_format() {
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(LoggingConfig::new().set_format(LogFormat::Binary))
+            .write(
+                &mut buf,
+                &Record::builder()
+                    .args(format_args!("{} samples", 2048))
+                    .level(Level::Debug)
+                    .target("airspy")
+                    .build(),
+            )
+            .unwrap();
+
+        let record = binary::BinaryReader::new(buf.as_slice())
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.level, Level::Debug);
+        assert_eq!(record.target, "airspy");
+        assert_eq!(record.message, "2048 samples");
+    }
+
+    #[test]
+    fn test_sequence_numbers() {
+        let formatter = RecordFormatter::new(LoggingConfig::new().set_sequence_numbers(true));
+        let mut sequences = Vec::new();
+        for _ in 0..2 {
+            let mut buf: Vec<u8> = Vec::new();
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("message"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+            let output = String::from_utf8(buf).unwrap();
+            let (sequence, rest) = output[1..].split_once(']').unwrap();
+            assert!(rest.starts_with('['));
+            sequences.push(sequence.parse::<u64>().unwrap());
+        }
+        assert_eq!(sequences[1], sequences[0] + 1);
+
+        let output = render(LoggingConfig::new(), Level::Info, "app", "message");
+        assert!(!output.starts_with("[1"));
+    }
+
+    #[test]
+    fn test_key_values() {
+        let fields: &[(&str, log::kv::Value)] = &[
+            ("icao", log::kv::Value::from("a1b2c3")),
+            ("altitude", log::kv::Value::from(35000)),
+        ];
+        let record = |config: LoggingConfig| {
+            let mut buf: Vec<u8> = Vec::new();
+            RecordFormatter::new(config)
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("position"))
+                        .level(Level::Info)
+                        .key_values(&fields)
+                        .build(),
+                )
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let format = |format| LoggingConfig::new().set_format(format);
+
+        assert!(
+            record(format(LogFormat::Human)).ends_with("]position icao=a1b2c3 altitude=35000\n")
+        );
+        assert!(record(format(LogFormat::Json))
+            .ends_with("\"message\":\"position\",\"icao\":\"a1b2c3\",\"altitude\":35000}\n"));
+        assert!(record(format(LogFormat::Csv))
+            .ends_with(",INFO,,position,icao=a1b2c3 altitude=35000\n"));
+
+        let counted = |format| {
+            LoggingConfig::new()
+                .set_format(format)
+                .set_field_count(true)
+        };
+        assert!(record(counted(LogFormat::Human)).ends_with(" altitude=35000 fields=2\n"));
+        assert!(record(counted(LogFormat::Json)).ends_with(",\"altitude\":35000,\"fields\":2}\n"));
+    }
+
+    #[test]
+    fn test_columns_format() {
+        let config = LoggingConfig::new()
+            .set_format(LogFormat::Columns)
+            .set_time_format("%H:%M:%S")
+            .set_header(true)
+            .set_column_widths(ColumnWidths {
+                target: 8,
+                ..ColumnWidths::default()
+            });
+        let output = render(
+            config.clone(),
+            Level::Warn,
+            "acars_router::net",
+            "lost link",
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "level timestamp           target   message");
+        let (level, rest) = lines[1].split_at(6);
+        assert_eq!(level, "WARN  ");
+        let (time, rest) = rest.split_at(20);
+        assert!(chrono::NaiveTime::parse_from_str(time.trim_end(), "%H:%M:%S").is_ok());
+        assert_eq!(rest, "acars_ro lost link");
+
+        let widths = ColumnWidths {
+            message: Some(6),
+            ..ColumnWidths::default()
+        };
+        let output = render(
+            config.set_column_widths(widths).set_header(false),
+            Level::Info,
+            "app",
+            "abc",
+        );
+        assert!(output.ends_with(" app                      abc   \n"));
+        let output = render(
+            LoggingConfig::new()
+                .set_format(LogFormat::Columns)
+                .set_column_widths(widths),
+            Level::Info,
+            "app",
+            "truncated",
+        );
+        assert!(output.ends_with(" trunca\n"));
+    }
+
+    #[test]
+    fn test_common_log_format() {
+        let render_fields = |fields: &[(&str, log::kv::Value)]| {
+            let mut buf: Vec<u8> = Vec::new();
+            RecordFormatter::new(LoggingConfig::new().set_format(LogFormat::CommonLog))
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("request"))
+                        .level(Level::Info)
+                        .key_values(&fields)
+                        .build(),
+                )
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let mut fields = vec![
+            ("remote", log::kv::Value::from("192.0.2.7")),
+            ("method", log::kv::Value::from("GET")),
+            ("path", log::kv::Value::from("/status")),
+            ("status", log::kv::Value::from(200)),
+            ("bytes", log::kv::Value::from(512)),
+        ];
+        let line = render_fields(&fields);
+        let (start, rest) = line.split_once(" [").unwrap();
+        let (time, request) = rest.split_once("] ").unwrap();
+        assert_eq!(start, "192.0.2.7 - -");
+        assert!(chrono::DateTime::parse_from_str(time, "%d/%b/%Y:%H:%M:%S %z").is_ok());
+        assert_eq!(request, "\"GET /status HTTP/1.1\" 200 512\n");
+
+        fields[4] = ("bytes", log::kv::Value::from(0));
+        fields.push(("user_agent", log::kv::Value::from("curl/8.0")));
+        let line = render_fields(&fields);
+        assert!(line.ends_with("] \"GET /status HTTP/1.1\" 200 - \"-\" \"curl/8.0\"\n"));
+
+        let plain = strip_styles(&render_fields(&fields[..2]));
+        assert!(plain.starts_with("[INFO ]["));
+        assert!(plain.ends_with("]request remote=192.0.2.7 method=GET\n"));
+    }
+
+    #[test]
+    fn test_csv_format() {
+        let config = LoggingConfig::new().set_format(LogFormat::Csv);
+        let output = render(config, Level::Warn, "acars_router", "a, \"b\"\nc");
+        let (timestamp, rest) = output.split_once(',').unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        assert_eq!(rest, "WARN,acars_router,\"a, \"\"b\"\"\nc\",\n");
+
+        let config = LoggingConfig::new()
+            .set_format(LogFormat::Csv)
+            .set_header(true)
+            .set_sequence_numbers(true);
+        let output = render(config, Level::Info, "app", "plain");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "sequence,timestamp,level,target,message,fields");
+        assert!(lines[1].ends_with(",INFO,app,plain,"));
+        assert!(lines[1].starts_with(|c: char| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_collapse_repeats() {
+        let formatter = RecordFormatter::new(LoggingConfig::new().set_collapse_repeats(true));
+        let mut buf: Vec<u8> = Vec::new();
+        for message in ["heartbeat", "heartbeat", "heartbeat", "tuned", "heartbeat"] {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+        }
+
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let messages: Vec<&str> = output
+            .lines()
+            .map(|line| line.rsplit_once(']').unwrap().1)
+            .collect();
+        assert_eq!(
+            messages,
+            ["heartbeat", "heartbeat (x3)", "tuned", "heartbeat"]
+        );
+    }
+
+    #[test]
+    fn test_sample_rate() {
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new()
+                .set_sample_rate(Level::Trace, 3)
+                .set_sample_rate(Level::Debug, 0),
+        );
+        let mut buf: Vec<u8> = Vec::new();
+        for index in 0..7 {
+            for level in [Level::Trace, Level::Debug] {
+                formatter
+                    .write(
+                        &mut buf,
+                        &Record::builder()
+                            .args(format_args!("{index}"))
+                            .level(level)
+                            .build(),
+                    )
+                    .unwrap();
+            }
+        }
+
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let trace: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("[TRACE]"))
+            .map(|line| line.rsplit_once(']').unwrap().1)
+            .collect();
+        assert_eq!(trace, ["0", "3", "6"]);
+        assert_eq!(output.matches("[DEBUG]").count(), 7);
+    }
+
+    #[test]
+    fn test_message_filters() {
+        let config = LoggingConfig::new()
+            .set_deny_messages(&["heartbeat ok", "noise"])
+            .set_min_message_length(3);
+        assert_eq!(
+            render(config.clone(), Level::Info, "app", "heartbeat ok (12ms)"),
+            ""
+        );
+        assert_eq!(
+            render(config.clone(), Level::Error, "app", "some noise"),
+            ""
+        );
+        assert_eq!(render(config.clone(), Level::Info, "app", "ok"), "");
+        assert!(
+            render(config, Level::Info, "app", "heartbeat failed").ends_with("]heartbeat failed\n")
+        );
+    }
+
+    #[test]
+    fn test_slow_log() {
+        fn render_timed(config: &LoggingConfig, duration: &dyn log::kv::ToValue) -> String {
+            let fields = [("duration_ms", duration.to_value())];
+            let mut buf = Vec::new();
+            RecordFormatter::new(config.clone())
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("decoded"))
+                        .level(Level::Info)
+                        .key_values(&fields)
+                        .build(),
+                )
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+
+        let config = LoggingConfig::new().set_slow_log_threshold(Some(Duration::from_millis(50)));
+        assert_eq!(render_timed(&config, &12u64), "");
+        assert_eq!(render_timed(&config, &49.9f64), "");
+        assert!(render_timed(&config, &50u64).contains("duration_ms=50"));
+        assert!(render_timed(&config, &"75.5").contains("duration_ms=75.5"));
+        assert!(render(config.clone(), Level::Info, "app", "untimed").ends_with("]untimed\n"));
+        let config = config.set_slow_log_keep_untimed(false);
+        assert_eq!(render(config.clone(), Level::Info, "app", "untimed"), "");
+        assert_eq!(render_timed(&config, &"slow"), "");
+    }
+
+    #[test]
+    fn test_level_cooldown() {
+        let config = LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_level_cooldown(Level::Warn, Some(Duration::from_millis(200)));
+        let formatter = RecordFormatter::new(config);
+        let log = |level: Level, message: &str| {
+            let mut buf = Vec::new();
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(level)
+                        .target("app")
+                        .build(),
+                )
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert!(log(Level::Warn, "gain clipped").ends_with("]gain clipped\n"));
+        assert_eq!(log(Level::Warn, "sync lost"), "");
+        assert_eq!(log(Level::Warn, "crc error"), "");
+        assert!(log(Level::Info, "tuned").ends_with("]tuned\n"));
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(log(Level::Warn, "gain clipped").ends_with("]gain clipped (2 suppressed)\n"));
+        assert_eq!(log(Level::Warn, "gain clipped"), "");
+    }
+
+    #[test]
+    fn test_target_labels() {
+        let audit = Style::new().bold();
+        let config = LoggingConfig::new()
+            .set_target_label("audit", "SECURITY", audit)
+            .set_target_label("audit", "AUDIT", audit);
+        let output = render(config.clone(), Level::Info, "audit", "user logged in");
+        assert!(output.starts_with(&format!("[{audit}AUDIT{audit:#}]")));
+        assert!(
+            strip_styles(&render(config.clone(), Level::Error, "audit::sub", "x"))
+                .starts_with("[ERROR]")
+        );
+        assert!(
+            strip_styles(&render(config.clone(), Level::Warn, "app", "x")).starts_with("[WARN ]")
+        );
+        assert!(render(
+            config.set_format(LogFormat::Json),
+            Level::Info,
+            "audit",
+            "x"
+        )
+        .contains("\"level\":\"INFO\""));
+    }
+
+    #[test]
+    fn test_time_deltas() {
+        let formatter = RecordFormatter::new(LoggingConfig::new().set_time_deltas(true));
+        let mut buf: Vec<u8> = Vec::new();
+        for pause in [0, 20] {
+            std::thread::sleep(Duration::from_millis(pause));
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("tick"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let deltas: Vec<u128> = output
+            .lines()
+            .map(|line| {
+                let (_, delta) = line.split_once("][+").unwrap();
+                delta.split_once("ms]").unwrap().0.parse().unwrap()
+            })
+            .collect();
+        assert_eq!(deltas[0], 0);
+        assert!(deltas[1] >= 20);
+    }
+
+    #[test]
+    fn test_uptime() {
+        let config = LoggingConfig::new().set_uptime(true);
+        let formatter = RecordFormatter::new(config.clone());
+        let mut buf: Vec<u8> = Vec::new();
+        for pause in [0, 20] {
+            std::thread::sleep(Duration::from_millis(pause));
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("tick"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let uptimes: Vec<f64> = output
+            .lines()
+            .map(|line| {
+                let (_, uptime) = line.split_once("][up ").unwrap();
+                uptime.split_once("s]").unwrap().0.parse().unwrap()
+            })
+            .collect();
+        // rounded to milliseconds, so allow for one of rounding
+        assert!(uptimes[1] - uptimes[0] >= 0.019);
+
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config.set_format(LogFormat::Json))
+            .write(
+                &mut buf,
+                &Record::builder().args(format_args!("tick")).build(),
+            )
+            .unwrap();
+        assert!(String::from_utf8(buf).unwrap().contains(",\"uptime\":"));
+    }
+
+    #[test]
+    fn test_aligned_fields() {
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new()
+                .set_aligned_fields(Some(16))
+                .set_shown_fields(&["icao", "alt", "rssi"]),
+        );
+        let mut buf: Vec<u8> = Vec::new();
+        let records: [(&str, &[(&str, &str)]); 3] = [
+            (
+                "Position",
+                &[("rssi", "-12.5"), ("icao", "a1b2c3"), ("alt", "35000")],
+            ),
+            (
+                "Position",
+                &[("icao", "4ca7"), ("alt", "900"), ("squawk", "7000")],
+            ),
+            ("Decoded frame", &[("alt", "35000")]),
+        ];
+        for (message, pairs) in records {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .key_values(&pairs)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let lines: Vec<&str> = output
+            .lines()
+            .map(|line| line.split_once(']').unwrap().1.split_once(']').unwrap().1)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "Position         icao=a1b2c3 alt=35000 rssi=-12.5",
+                "Position         icao=4ca7   alt=900",
+                "Decoded frame    alt=35000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_epoch_timestamps() {
+        use chrono::TimeZone;
+
+        let time = Timestamp {
+            now: Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap()
+                + chrono::Duration::microseconds(5_001),
+            format: DEFAULT_TIME_FORMAT,
+            utc_format: Some("%H:%M:%SZ"),
+            rfc3339: Some(SecondsFormat::Secs),
+            elapsed: None,
+            epoch: Some((12, 3)),
+            utc: false,
+            style: Style::new(),
+        };
+        assert_eq!(time.to_string(), "001000000000.005");
+        let time = Timestamp {
+            epoch: Some((10, 0)),
+            ..time
+        };
+        assert_eq!(time.to_string(), "1000000000");
+        let time = Timestamp {
+            epoch: Some((4, 6)),
+            ..time
+        };
+        assert_eq!(time.to_string(), "1000000000.005001");
+
+        let config = LoggingConfig::new()
+            .set_epoch_timestamps(Some(11))
+            .set_level_time_precision(Level::Debug, TimePrecision::Seconds)
+            .set_clock(|| Utc.with_ymd_and_hms(2021, 8, 22, 22, 49, 1).unwrap());
+        assert!(
+            strip_styles(&render(config.clone(), Level::Info, "app", "x"))
+                .starts_with("[INFO ][01629672541.000]x")
+        );
+        assert!(strip_styles(&render(config, Level::Debug, "app", "x"))
+            .starts_with("[DEBUG][01629672541]x"));
+    }
+
+    #[test]
+    fn test_elapsed_time() {
+        let time = Timestamp {
+            now: Utc::now(),
+            format: DEFAULT_TIME_FORMAT,
+            utc_format: None,
+            rfc3339: None,
+            elapsed: Some(Duration::from_secs(62)),
+            epoch: None,
+            utc: false,
+            style: Style::new(),
+        };
+        assert_eq!(time.to_string(), "01:02");
+        let time = Timestamp {
+            elapsed: Some(Duration::from_secs(100 * 60 + 5)),
+            ..time
+        };
+        assert_eq!(time.to_string(), "100:05");
+
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(LoggingConfig::tiny())
+            .write(
+                &mut buf,
+                &Record::builder().args(format_args!("tuned")).build(),
+            )
+            .unwrap();
+        let line = strip_styles(&String::from_utf8(buf).unwrap());
+        assert_eq!(line.len(), "00:00 I tuned\n".len());
+        assert!(line.ends_with(" I tuned\n"));
+    }
+
+    #[test]
+    fn test_clock() {
+        use chrono::TimeZone;
+
+        let clock = || Utc.with_ymd_and_hms(2021, 8, 22, 22, 49, 1).unwrap();
+        let config = LoggingConfig::new()
+            .set_hostname(Some(String::from("receiver")))
+            .set_clock(clock);
+        let record = Record::builder()
+            .args(format_args!("tuned"))
+            .target("app")
+            .build();
+
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config.clone().set_format(LogFormat::Json))
+            .write(&mut buf, &record)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"timestamp\":\"2021-08-22T22:49:01.000Z\",\"host\":\"receiver\",\"level\":\"INFO\",\"target\":\"app\",\"message\":\"tuned\"}\n"
+        );
+
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config.clone().set_format(LogFormat::Csv))
+            .write(&mut buf, &record)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "2021-08-22T22:49:01.000Z,INFO,app,tuned,\n"
+        );
+
+        // human lines are in the local timezone of the clock's time
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(config)
+            .write_human(&mut buf, &record, None)
+            .unwrap();
+        let local = clock().with_timezone(&Local).format(DEFAULT_TIME_FORMAT);
+        assert!(strip_styles(&String::from_utf8(buf).unwrap())
+            .starts_with(&format!("[INFO ][{local}]")));
+    }
+
+    #[test]
+    fn test_utc_timestamps() {
+        use chrono::TimeZone;
+
+        let config = LoggingConfig::new()
+            .set_utc_timestamps(true)
+            .set_clock(|| Utc.with_ymd_and_hms(2021, 8, 22, 22, 49, 1).unwrap());
+        let record = Record::builder().args(format_args!("tuned")).build();
+        let human = |config: LoggingConfig| {
+            let mut buf: Vec<u8> = Vec::new();
+            RecordFormatter::new(config)
+                .write_human(&mut buf, &record, None)
+                .unwrap();
+            strip_styles(&String::from_utf8(buf).unwrap())
+        };
+        assert_eq!(human(config.clone()), "[INFO ][2021-08-22T22:49:01]tuned\n");
+        assert_eq!(
+            human(config.clone().set_timezone_suffix(TimezoneSuffix::Offset)),
+            "[INFO ][2021-08-22T22:49:01+00:00]tuned\n"
+        );
+        assert_eq!(
+            human(
+                config
+                    .set_rfc3339_timestamps(true)
+                    .set_time_precision(TimePrecision::Seconds)
+            ),
+            "[INFO ][2021-08-22T22:49:01Z]tuned\n"
+        );
+    }
+
+    #[test]
+    fn test_show_level() {
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new()
+                .set_show_level(false)
+                .set_header(true)
+                .set_sequence_numbers(true),
+        );
+        let mut buf: Vec<u8> = Vec::new();
+        formatter
+            .write(
+                &mut buf,
+                &Record::builder()
+                    .args(format_args!("tuned"))
+                    .level(Level::Warn)
+                    .build(),
+            )
+            .unwrap();
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "[sequence][timestamp]message");
+        assert!(!lines[1].contains("WARN"));
+        assert_eq!(lines[1].matches('[').count(), 2);
+        assert!(lines[1].ends_with("]tuned"));
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        let red = Style::new().fg_color(Some(anstyle::AnsiColor::Red.into()));
+        let bold = Style::new().bold();
+        let rules = [(String::from("a1b2"), red), (String::from("a1"), bold)];
+        assert_eq!(
+            highlight_matches("a1b2c3 then a1 at 1090", &rules, Style::new()),
+            format!("{red}a1b2{red:#}c3 then {bold}a1{bold:#} at 1090")
+        );
+        assert_eq!(
+            highlight_matches("nothing", &rules, Style::new()),
+            "nothing"
+        );
+
+        // the style of a fatal line resumes after each match
+        let line = Style::new().fg_color(Some(anstyle::AnsiColor::Red.into()));
+        assert_eq!(
+            highlight_matches("lost a1", &[(String::from("a1"), bold)], line),
+            format!("lost {bold}a1{bold:#}{line}")
+        );
+
+        let mut buf: Vec<u8> = Vec::new();
+        RecordFormatter::new(LoggingConfig::new().add_highlight("1090", bold))
+            .write_human(
+                &mut buf,
+                &Record::builder()
+                    .args(format_args!("tuned to 1090"))
+                    .build(),
+                None,
+            )
+            .unwrap();
+        assert!(String::from_utf8(buf)
+            .unwrap()
+            .ends_with(&format!("]tuned to {bold}1090{bold:#}\n")));
+    }
+
+    #[test]
+    fn test_collapse_backtraces() {
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new()
+                .set_collapse_backtraces(true)
+                .set_format(LogFormat::Json),
+        );
+        let mut buf: Vec<u8> = Vec::new();
+        for message in [
+            "decode failed\n  at decode.rs:10\n  at main.rs:3",
+            "single line",
+            "decode failed again\n  at decode.rs:10\n  at main.rs:3",
+            "other failure\n  at other.rs:1",
+        ] {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(Level::Error)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].contains("decode failed\\n  at decode.rs:10"));
+        assert!(lines[1].contains("single line"));
+        assert!(lines[2].contains("decode failed again\\n(backtrace identical to previous)"));
+        assert!(lines[3].contains("other failure\\n  at other.rs:1"));
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(
+            wrap_text("tuned to 1090 MHz", 12, 4, 4),
+            "tuned to\n    1090 MHz"
+        );
+        assert_eq!(wrap_text("abcdefghij", 8, 4, 2), "abcd\n  efghij");
+        assert_eq!(wrap_text("one\ntwo", 80, 0, 3), "one\n   two");
+        assert_eq!(wrap_text("short", 80, 10, 10), "short");
+
+        let config = LoggingConfig::compact().set_wrap_column(Some(30));
+        let output = strip_styles(&render(
+            config,
+            Level::Info,
+            "app",
+            "the receiver lost sync with the satellite",
+        ));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.chars().count() <= 30));
+        assert_eq!(lines[1], "           sync with the");
+    }
+
+    #[test]
+    fn test_drop_summary() {
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new()
+                .set_sample_rate(Level::Info, 2)
+                .set_drop_summary_interval(Some(Duration::ZERO)),
+        );
+        let mut buf: Vec<u8> = Vec::new();
+        for message in ["first", "second", "third"] {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+        }
+
+        // the counters are shared with the other tests, so only the position
+        // of the summary is certain, not its counts
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(!output.contains("second"));
+        assert!(lines[lines.len() - 1].ends_with("]third"));
+        assert!(lines[lines.len() - 2].contains("]Not written in the last 0s: "));
+    }
+
+    #[test]
+    fn test_rate_status() {
+        let window = Duration::from_secs(10);
+        let start = Instant::now();
+        let mut state = RateState::new();
+        for millis in [0, 0, 1, 500, 9_000] {
+            state.note(start + Duration::from_millis(millis), window);
+        }
+        // the two records of the first millisecond share an entry
+        assert_eq!(state.emits.len(), 4);
+        assert_eq!(state.rate(start + Duration::from_secs(10), window), 0.5);
+        // the first three have left the window
+        assert_eq!(
+            state.rate(start + Duration::from_millis(10_400), window),
+            0.2
+        );
+        assert_eq!(
+            rate_status(0.2, window),
+            "Logging 0.2 records/s over the last 10s"
+        );
+
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new().set_rate_status_interval(Some(Duration::ZERO)),
+        );
+        let mut buf: Vec<u8> = Vec::new();
+        for message in ["first", "second"] {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with("]Logging 0.0 records/s over the last 10s"));
+        assert!(lines[2].ends_with("]Logging 0.1 records/s over the last 10s"));
+        assert!(lines[3].ends_with("]second"));
+    }
+
+    #[test]
+    fn test_write_latency_status() {
+        use crate::writer::TimedWriter;
+
+        let formatter = RecordFormatter::new(
+            LoggingConfig::new().set_write_latency_interval(Some(Duration::ZERO)),
+        );
+        let mut output = TimedWriter::new(Vec::new(), formatter.latency().unwrap());
+        for message in ["first", "second"] {
+            let mut buf: Vec<u8> = Vec::new();
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder().args(format_args!("{message}")).build(),
+                )
+                .unwrap();
+            output.write_all(&buf).unwrap();
+            output.flush().unwrap();
+        }
+        let mut buf: Vec<u8> = Vec::new();
+        formatter
+            .write(
+                &mut buf,
+                &Record::builder().args(format_args!("third")).build(),
+            )
+            .unwrap();
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let lines: Vec<&str> = output.lines().collect();
+        // no status ahead of the first record, as nothing was written before it
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("]Write latency p50 "));
+        assert!(lines[0].ends_with(" over 1 writes in the last 0s"));
+        assert!(lines[1].ends_with("]third"));
+    }
+
+    #[test]
+    fn test_header() {
+        let formatter = RecordFormatter::new(LoggingConfig::new().set_header(true));
+        let mut buf: Vec<u8> = Vec::new();
+        for message in ["first", "second"] {
+            formatter
+                .write(
+                    &mut buf,
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(Level::Info)
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = strip_styles(&String::from_utf8(buf).unwrap());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "[level][timestamp]message");
+
+        let config = LoggingConfig::compact()
+            .set_header(true)
+            .set_sequence_numbers(true);
+        let output = render(config, Level::Info, "app", "hello");
+        assert!(output.starts_with("sequence timestamp level message\n"));
+
+        let config = LoggingConfig::new()
+            .set_header(true)
+            .set_format(LogFormat::Json);
+        let output = render(config, Level::Info, "app", "hello");
+        assert!(output.starts_with("{\"timestamp\":"));
+        assert_eq!(output.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_color_min_level() {
+        let config = LoggingConfig::new();
+        assert!(render(config.clone(), Level::Trace, "app", "message").contains('\x1b'));
+
+        let config = config.set_color_min_level(Level::Error);
+        assert!(render(config.clone(), Level::Error, "app", "message").contains('\x1b'));
+        let warn = render(config, Level::Warn, "app", "message");
+        assert!(!warn.contains('\x1b'));
+        assert!(warn.starts_with("[WARN ]["));
+    }
+
+    #[test]
+    fn test_level_border() {
+        let config = LoggingConfig::new()
+            .set_color(ColorPreference::Always)
+            .set_level_border(Some('▎'))
+            .set_color_min_level(Level::Error);
+        let warn = ColorTheme::default().warn;
+        let output = render(config.clone(), Level::Warn, "app", "message");
+        assert!(output.starts_with(&format!("{warn}▎{warn:#} [WARN ][")));
+        assert_eq!(strip_styles(&output).matches('▎').count(), 1);
+
+        let fatal = render(config.clone(), Level::Error, FATAL_TARGET, "giving up");
+        let error = ColorTheme::default().error;
+        assert!(fatal.starts_with(&format!("{error}▎{error:#} {error}[FATAL]")));
+
+        for config in [
+            config.clone().set_color(ColorPreference::Never),
+            config.set_level_border(None),
+        ] {
+            assert!(render(config, Level::Warn, "app", "message").starts_with("[WARN ]["));
+        }
+    }
+
+    #[test]
+    fn test_custom_level_names() {
+        let config = LoggingConfig::new()
+            .set_level_name(Level::Info, "NOTE")
+            .set_level_name(Level::Warn, "WARNUNG");
+        let info = strip_styles(&render(config.clone(), Level::Info, "app", "message"));
+        let warn = strip_styles(&render(config.clone(), Level::Warn, "app", "message"));
+        let error = strip_styles(&render(config.clone(), Level::Error, "app", "message"));
+        assert!(info.starts_with("[NOTE   ]["));
+        assert!(warn.starts_with("[WARNUNG]["));
+        assert!(error.starts_with("[ERROR  ]["));
+
+        let config = config
+            .set_level_name(Level::Debug, "Ébauche")
+            .set_level_label(LevelLabel::Short);
+        let debug = strip_styles(&render(config.clone(), Level::Debug, "app", "message"));
+        assert!(debug.starts_with("[É]["));
+
+        let json = render(
+            config.set_format(LogFormat::Json),
+            Level::Info,
+            "app",
+            "message",
+        );
+        assert!(json.contains("\"level\":\"INFO\""));
+    }
+
+    #[test]
+    fn test_level_case() {
+        let config = LoggingConfig::new().set_level_case(LevelCase::Lower);
+        let info = strip_styles(&render(config.clone(), Level::Info, "app", "message"));
+        assert!(info.starts_with("[info ]["));
+
+        let config = config
+            .set_level_case(LevelCase::Title)
+            .set_level_name(Level::Warn, "WARNUNG");
+        let warn = strip_styles(&render(config.clone(), Level::Warn, "app", "message"));
+        assert!(warn.starts_with("[Warnung]["));
+
+        let short = config
+            .clone()
+            .set_level_case(LevelCase::Lower)
+            .set_level_label(LevelLabel::Short);
+        let error = strip_styles(&render(short, Level::Error, "app", "message"));
+        assert!(error.starts_with("[e]["));
+
+        let json = render(
+            config.set_format(LogFormat::Json),
+            Level::Info,
+            "app",
+            "message",
+        );
+        assert!(json.contains("\"level\":\"INFO\""));
+    }
+
+    #[test]
+    fn test_timestamp_display() {
+        let style = ColorTheme::default().timestamp;
+        let now = Utc::now();
+        let mut buf: Vec<u8> = Vec::with_capacity(256);
+        let time = Timestamp {
+            now,
+            format: DEFAULT_TIME_FORMAT,
+            utc_format: None,
+            rfc3339: None,
+            elapsed: None,
+            epoch: None,
+            utc: false,
+            style,
+        };
+        write!(buf, "{time}").unwrap();
+
+        let mut expected = Vec::new();
+        write!(
+            expected,
+            "{style}{}{style:#}",
+            now.with_timezone(&Local).format(DEFAULT_TIME_FORMAT)
+        )
+        .unwrap();
+        assert_eq!(buf, expected);
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! The heartbeat of `LoggingConfig::set_heartbeat_interval`, logged by a
+//! background thread when nothing else was written for an interval
+
+use log::{Level, Record};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// When the last record was written, by any output
+static LAST_EMIT: Mutex<Option<Instant>> = Mutex::new(None);
+/// Set when the `LoggingGuard` is dropped, so no heartbeat follows the summary
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// Note a written record, putting off the next heartbeat
+pub(crate) fn note_emit() {
+    *LAST_EMIT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Instant::now());
+}
+
+/// How long to wait for the next heartbeat after the last record at `last`,
+/// or `None` if it is due at `now`
+fn wait(last: Instant, now: Instant, interval: Duration) -> Option<Duration> {
+    let idle = now.saturating_duration_since(last);
+    (idle < interval).then(|| interval - idle)
+}
+
+/// Start the thread logging a heartbeat at `level` whenever nothing was
+/// written for `interval`. Without threads, as on wasm32, there is no heartbeat.
+pub(crate) fn start(interval: Duration, level: Level) {
+    note_emit();
+    let _ = thread::Builder::new()
+        .name(String::from("sdre-rust-logging-heartbeat"))
+        .spawn(move || loop {
+            let last = LAST_EMIT
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .unwrap_or_else(Instant::now);
+            match wait(last, Instant::now(), interval) {
+                Some(remaining) => thread::sleep(remaining),
+                None if STOPPED.load(Ordering::Relaxed) => return,
+                None => {
+                    log::logger().log(
+                        &Record::builder()
+                            .args(format_args!(
+                                "Heartbeat: no records in the last {}s",
+                                interval.as_secs_f64()
+                            ))
+                            .level(level)
+                            .target("sdre_rust_logging")
+                            .build(),
+                    );
+                    // the heartbeat may be filtered out, so it is noted here
+                    note_emit();
+                }
+            }
+        });
+}
+
+/// Stop logging heartbeats
+pub(crate) fn stop() {
+    STOPPED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wait() {
+        let interval = Duration::from_secs(30);
+        let last = Instant::now();
+        assert_eq!(wait(last, last, interval), Some(interval));
+        assert_eq!(
+            wait(last, last + Duration::from_secs(20), interval),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(wait(last, last + interval, interval), None);
+        assert_eq!(wait(last, last + Duration::from_secs(90), interval), None);
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use log::LevelFilter;
+
+/// The level used when a level can not be parsed, unless the caller passes
+/// its own default to the `_or` functions
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Convert an integer to a `LevelFilter`<br><br>
+/// The integers match up to the log crate's levels: 1 is error, 2 warn, 3 info,
+/// 4 debug and 5 trace. Anything else defaults to info.
+#[must_use]
+pub fn level_from_int(level: u64) -> LevelFilter {
+    level_from_int_or(level, DEFAULT_LEVEL)
+}
+
+/// Convert an integer to a `LevelFilter` as `level_from_int` does, with
+/// anything out of range resolving to `default`
+#[must_use]
+pub fn level_from_int_or(level: u64, default: LevelFilter) -> LevelFilter {
+    match level {
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        5 => LevelFilter::Trace,
+        _ => default,
+    }
+}
+
+/// Convert a level name in any case to a `LevelFilter`<br><br>
+/// The names are `"error"`, `"warn"`, `"info"`, `"debug"` and `"trace"`.
+/// Anything else defaults to info. This is the mapping `set_logging_level`
+/// uses on a `&str`, without installing a logger.
+#[must_use]
+pub fn level_from_str(level: &str) -> LevelFilter {
+    level_from_str_or(level, DEFAULT_LEVEL)
+}
+
+/// Convert a level name to a `LevelFilter` as `level_from_str` does, with
+/// unknown names resolving to `default`
+#[must_use]
+pub fn level_from_str_or(level: &str, default: LevelFilter) -> LevelFilter {
+    LEVEL_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(level))
+        .map_or(default, |(_, level)| *level)
+}
+
+/// The level of the first of the environment variables `names` that is set,
+/// or `default` if none is<br><br>
+/// Meant for migrating from one variable name to another, with the new name
+/// first: `level_from_envs(&["MYAPP_LOG", "LOG_LEVEL"], LevelFilter::Info)`.
+/// A variable that is empty, or not valid unicode, counts as not set and the
+/// next one is checked. The value of the first set variable decides the
+/// level, even if it is not one: integers map as in `level_from_int_or` and
+/// names as in `level_from_str_or`, with `default` for anything else.
+#[must_use]
+pub fn level_from_envs(names: &[&str], default: LevelFilter) -> LevelFilter {
+    level_from_envs_with(names, default, |name| std::env::var(name).ok())
+}
+
+fn level_from_envs_with(
+    names: &[&str],
+    default: LevelFilter,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> LevelFilter {
+    let Some(value) = names
+        .iter()
+        .filter_map(|name| lookup(name))
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+    else {
+        return default;
+    };
+    match value.parse::<u64>() {
+        Ok(level) => level_from_int_or(level, default),
+        Err(_) => level_from_str_or(&value, default),
+    }
+}
+
+/// The names `level_from_str` accepts and their levels, most severe first
+const LEVEL_NAMES: [(&str, LevelFilter); 5] = [
+    ("error", LevelFilter::Error),
+    ("warn", LevelFilter::Warn),
+    ("info", LevelFilter::Info),
+    ("debug", LevelFilter::Debug),
+    ("trace", LevelFilter::Trace),
+];
+
+/// The level names `level_from_str` and `set_logging_level` accept, most
+/// severe first, for help text and input validation<br><br>
+/// `format!("--log-level <{}>", level_names().join("|"))` is
+/// `--log-level <error|warn|info|debug|trace>`. Names are accepted in any
+/// case. `LevelSetting` also accepts `"off"`.
+#[must_use]
+pub fn level_names() -> Vec<&'static str> {
+    LEVEL_NAMES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Convert a `LevelFilter` to its integer, the inverse of `level_from_int`<br><br>
+/// `LevelFilter::Off` is 0, which `level_from_int` does not map back to off.
+#[must_use]
+pub fn int_from_level(level: LevelFilter) -> u64 {
+    match level {
+        LevelFilter::Off => 0,
+        LevelFilter::Error => 1,
+        LevelFilter::Warn => 2,
+        LevelFilter::Info => 3,
+        LevelFilter::Debug => 4,
+        LevelFilter::Trace => 5,
+    }
+}
+
+/// A `LevelFilter` that can be read from and written to config files<br><br>
+/// Deserializes from the level names accepted by `set_logging_level` on a
+/// `&str` (`"error"`, `"warn"`, `"info"`, `"debug"`, `"trace"`, in any case)
+/// plus `"off"`, or from the integers of `level_from_int` plus 0 for off.
+/// Unlike `set_logging_level`, anything else is an error rather than info, so
+/// a typo in a config file is reported. Serializes as the lowercase name.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelSetting(pub LevelFilter);
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LevelSetting {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.as_str().to_lowercase())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LevelSetting {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LevelVisitor;
+
+        impl serde::de::Visitor<'_> for LevelVisitor {
+            type Value = LevelSetting;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a level name such as \"info\" or an integer from 0 to 5")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                if value.eq_ignore_ascii_case("off") {
+                    return Ok(LevelSetting(LevelFilter::Off));
+                }
+                value
+                    .parse::<log::Level>()
+                    .map(|level| LevelSetting(level.to_level_filter()))
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                match value {
+                    0 => Ok(LevelSetting(LevelFilter::Off)),
+                    1..=5 => Ok(LevelSetting(level_from_int(value))),
+                    _ => Err(E::invalid_value(
+                        serde::de::Unexpected::Unsigned(value),
+                        &self,
+                    )),
+                }
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                match u64::try_from(value) {
+                    Ok(value) => self.visit_u64(value),
+                    Err(_) => Err(E::invalid_value(
+                        serde::de::Unexpected::Signed(value),
+                        &self,
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(LevelVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_custom_default() {
+        assert_eq!(level_from_int_or(9, LevelFilter::Warn), LevelFilter::Warn);
+        assert_eq!(level_from_int_or(4, LevelFilter::Warn), LevelFilter::Debug);
+        assert_eq!(
+            level_from_str_or("loud", LevelFilter::Error),
+            LevelFilter::Error
+        );
+        assert_eq!(
+            level_from_str_or("TRACE", LevelFilter::Error),
+            LevelFilter::Trace
+        );
+    }
+
+    #[test]
+    fn test_level_from_str() {
+        assert_eq!(level_from_str("Debug"), LevelFilter::Debug);
+        assert_eq!(level_from_str("warn"), LevelFilter::Warn);
+        assert_eq!(level_from_str("off"), LevelFilter::Info);
+        assert_eq!(level_from_str(""), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_level_from_envs() {
+        let lookup = |name: &str| match name {
+            "MYAPP_LOG" => Some(String::from("debug")),
+            "LOG_LEVEL" => Some(String::from("4")),
+            "OLD_LOG" => Some(String::from("error")),
+            "EMPTY_LOG" => Some(String::from(" ")),
+            "BAD_LOG" => Some(String::from("loud")),
+            _ => None,
+        };
+        let level = |names: &[&str]| level_from_envs_with(names, LevelFilter::Warn, lookup);
+        assert_eq!(level(&["MYAPP_LOG", "OLD_LOG"]), LevelFilter::Debug);
+        assert_eq!(level(&["OLD_LOG", "MYAPP_LOG"]), LevelFilter::Error);
+        assert_eq!(
+            level(&["UNSET", "EMPTY_LOG", "OLD_LOG"]),
+            LevelFilter::Error
+        );
+        assert_eq!(level(&["LOG_LEVEL"]), LevelFilter::Debug);
+        assert_eq!(level(&["BAD_LOG", "OLD_LOG"]), LevelFilter::Warn);
+        assert_eq!(level(&["UNSET", "EMPTY_LOG"]), LevelFilter::Warn);
+        assert_eq!(level(&[]), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn test_level_names() {
+        assert_eq!(level_names(), ["error", "warn", "info", "debug", "trace"]);
+        // every listed name parses, to its own level rather than the default
+        for name in level_names() {
+            let level = level_from_str_or(&name.to_uppercase(), LevelFilter::Off);
+            assert_eq!(level.as_str().to_lowercase(), name);
+        }
+    }
+
+    #[test]
+    fn test_int_round_trip() {
+        for level in 1..=5 {
+            assert_eq!(int_from_level(level_from_int(level)), level);
+        }
+
+        for level in LevelFilter::iter().skip(1) {
+            assert_eq!(level_from_int(int_from_level(level)), level);
+        }
+
+        assert_eq!(int_from_level(LevelFilter::Off), 0);
+        assert_eq!(level_from_int(0), LevelFilter::Info);
+        assert_eq!(level_from_int(u64::MAX), LevelFilter::Info);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_level_setting_serde() {
+        let parse = |json: &str| serde_json::from_str::<LevelSetting>(json).map(|level| level.0);
+        assert_eq!(parse("\"debug\"").unwrap(), LevelFilter::Debug);
+        assert_eq!(parse("\"WARN\"").unwrap(), LevelFilter::Warn);
+        assert_eq!(parse("\"off\"").unwrap(), LevelFilter::Off);
+        assert_eq!(parse("5").unwrap(), LevelFilter::Trace);
+        assert_eq!(parse("0").unwrap(), LevelFilter::Off);
+        assert!(parse("\"verbose\"").is_err());
+        assert!(parse("6").is_err());
+        assert!(parse("-1").is_err());
+        assert!(parse("true").is_err());
+
+        for level in LevelFilter::iter() {
+            let json = serde_json::to_string(&LevelSetting(level)).unwrap();
+            assert_eq!(parse(&json).unwrap(), level);
+        }
+    }
+}
@@ -15,14 +15,125 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
 
 extern crate chrono;
-extern crate env_logger;
 extern crate log;
 
-use anstyle::Color;
-use chrono::Local;
-use env_logger::{fmt::style::AnsiColor, Builder};
+#[cfg(all(feature = "android", target_os = "android"))]
+mod android;
+pub mod binary;
+mod channel;
+mod color;
+mod config;
+mod early;
+mod fields;
+mod filter;
+mod force;
+mod format;
+mod heartbeat;
+mod level;
+mod panic;
+mod recent;
+mod route;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(all(unix, feature = "signals"))]
+mod signals;
+mod spec;
+mod stats;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
+mod writer;
+
+/// Re-exported so themes can be built without depending on a matching anstyle version
+pub use anstyle;
+pub use binary::{BinaryReader, BinaryRecord};
+pub use channel::{ChannelLogger, LogLine};
+pub use color::{ColorPreference, ColorTheme, ThemeError};
+pub use config::{
+    ColumnWidths, EmptyMessage, LevelCase, LevelLabel, LogFormat, LogTarget, LoggingConfig,
+    ResumePolicy, Sink, TimePrecision, TimezoneSuffix,
+};
+pub use early::{capture_early_logs, capture_early_logs_strict};
+/// Re-exported for `LoggingConfig::set_builder_hook`
+pub use env_logger;
+pub use fields::{
+    clear_correlation_id, correlation_id, error_chain, set_correlation_id, set_global_fields,
+    BuildInfo,
+};
+pub use filter::{FilterError, ModuleFilter};
+pub use force::log_forced;
+#[cfg(feature = "serde")]
+pub use level::LevelSetting;
+pub use level::{
+    int_from_level, level_from_envs, level_from_int, level_from_int_or, level_from_str,
+    level_from_str_or, level_names, DEFAULT_LEVEL,
+};
+pub use panic::log_panics;
+pub use recent::recent_logs;
+#[cfg(feature = "serde")]
+pub use serialize::log_serialize;
+#[cfg(all(unix, feature = "signals"))]
+pub use signals::flush_on_signals;
+pub use spec::{enable_logging_spec, SpecError};
+
+/// The commonly used traits and types, for glob importing<br><br>
+/// `use sdre_rust_logging::prelude::*;`
+pub mod prelude {
+    #[cfg(feature = "serde")]
+    pub use crate::LevelSetting;
+    pub use crate::{
+        ColorPreference, ColorTheme, LevelCase, LevelLabel, LogFormat, LogTarget, LoggingConfig,
+        LoggingGuard, SetupLogging, TimePrecision, TimezoneSuffix,
+    };
+    pub use log::LevelFilter;
+}
+
+use env_logger::{Builder, Target, WriteStyle};
 use log::LevelFilter;
-use std::io::Write;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+static CURRENT_CONFIG: OnceLock<Arc<LoggingConfig>> = OnceLock::new();
+
+/// The configuration logging was initialized with<br><br>
+/// Returns `None` if logging has not been initialized by this crate, either
+/// because `enable_logging`/`init` was never called or because another logger
+/// was already installed. The returned value is a shared snapshot, cheap to
+/// clone; the running logger does not follow changes to copies made of it.
+#[must_use]
+pub fn current_config() -> Option<Arc<LoggingConfig>> {
+    CURRENT_CONFIG.get().cloned()
+}
+
+/// The level field of a human line for `level`, as its style and text<br><br>
+/// Uses the theme, level names and label settings of the running logger, or
+/// the defaults if logging has not been initialized by this crate, so e.g. a
+/// TUI can show log lines with the same colors as the console. The text is
+/// padded like the console output; `style.get_fg_color()` gives the color
+/// alone. See `styled_level` for the ANSI string.
+#[must_use]
+pub fn level_token(level: log::Level) -> (anstyle::Style, String) {
+    /// The config of `level_token` while logging is not initialized
+    static DEFAULT_CONFIG: OnceLock<LoggingConfig> = OnceLock::new();
+
+    let config = match CURRENT_CONFIG.get() {
+        Some(config) => config.as_ref(),
+        None => DEFAULT_CONFIG.get_or_init(LoggingConfig::default),
+    };
+    let width = format::level_width(config);
+    let text = format::level_text(config, config.level_name(level), width);
+    (config.current_theme().level(level), text)
+}
+
+/// The level field of a human line for `level` as a string with ANSI styling,
+/// e.g. `"\x1b[1m\x1b[33mWARN \x1b[0m"` with the default theme. See `level_token`.
+#[must_use]
+pub fn styled_level(level: log::Level) -> String {
+    let (style, text) = level_token(level);
+    format!("{style}{text}{style:#}")
+}
 
 /// Trait to setup logging
 /// To initialize logging, call `enable_logging` on a u8
@@ -35,6 +146,18 @@ pub trait SetupLogging {
     /// High levels are suppressed. For example, if the level is set to info, users will see
     /// info, warn, and error messages. They will not see debug or trace messages.
     fn set_logging_level(self) -> LevelFilter;
+    /// Set logging level as `set_logging_level` does, with values that are not
+    /// a level resolving to `default` instead of info<br><br>
+    /// `"loud".set_logging_level_or(LevelFilter::Warn)` is `LevelFilter::Warn`<br><br>
+    /// Implemented for the types of this crate; for other types it defaults
+    /// to `set_logging_level`, ignoring `default`.
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter
+    where
+        Self: Sized,
+    {
+        let _ = default;
+        self.set_logging_level()
+    }
     /// Enable logging<br><br>
     /// The output is colored and looks like this:<br>
     /// \[INFO \]\[2021-08-22T15:49:01\]This is an info message<br>
@@ -43,128 +166,748 @@ pub trait SetupLogging {
     /// \[ERROR\]\[2021-08-22T15:49:01\]This is an error message<br>
     /// \[WARN \]\[2021-08-22T15:49:01\]This is a warning message<br>
     /// \[OTHER\]\[2021-08-22T15:49:01\]This is a message with a different log level<br><br>
+    /// Labels such as `OTHER` are registered per target with `LoggingConfig::set_target_label`.<br>
     /// The level field is colored and bold if the terminal supports it.<br>
     fn enable_logging(&self);
 }
 
-fn set_builder(loglevel: LevelFilter) {
-    let _ = Builder::new()
-        .format(|buf, record| {
-            let time_style = anstyle::Style::new()
-                .fg_color(Some((159, 80, 1).into()))
-                .bold();
-
-            let level_style = match record.level() {
-                log::Level::Info => anstyle::Style::new()
-                    .fg_color(Some(Color::from(AnsiColor::Green)))
-                    .bold(),
-                log::Level::Debug => anstyle::Style::new()
-                    .fg_color(Some(Color::from(AnsiColor::Cyan)))
-                    .bold(),
-                log::Level::Trace => anstyle::Style::new()
-                    .fg_color(Some(Color::from(AnsiColor::Magenta)))
-                    .bold(),
-                log::Level::Error => anstyle::Style::new()
-                    .fg_color(Some(Color::from(AnsiColor::Red)))
-                    .bold(),
-                log::Level::Warn => anstyle::Style::new()
-                    .fg_color(Some(Color::from(AnsiColor::Yellow)))
-                    .bold(),
-            };
-
-            let level = format!("{level_style}{: <5}{level_style:#}", record.level());
-            let time = format!(
-                "{time_style}{}{time_style:#}",
-                Local::now().format("%Y-%m-%dT%H:%M:%S")
+/// Log a final error and flush the output before returning<br><br>
+/// Meant for the last message before the application exits. The record is
+/// logged at error level, labelled `FATAL` and the whole line is colored red:<br>
+/// \[FATAL\]\[2021-08-22T15:49:01\]Could not open the SDR device<br><br>
+/// `log_fatal_and_flush(format_args!("Could not open {}", device));`
+pub fn log_fatal_and_flush(args: fmt::Arguments) {
+    let logger = log::logger();
+    logger.log(
+        &log::Record::builder()
+            .args(args)
+            .level(log::Level::Error)
+            .target(format::FATAL_TARGET)
+            .build(),
+    );
+    logger.flush();
+    let _ = std::io::stderr().flush();
+}
+
+/// Initialize logging at the level of the first of the environment variables
+/// `names` that is set, or at `default` if none is<br><br>
+/// `let _guard = enable_logging_from_envs(&["MYAPP_LOG", "LOG_LEVEL"], LevelFilter::Info);`<br><br>
+/// Earlier names take precedence, so list the new name of a renamed variable
+/// first and keep the deprecated one after it. See `level_from_envs` for the
+/// values accepted.
+#[must_use = "dropping the guard ends logging setup; bind it with let _guard"]
+pub fn enable_logging_from_envs(names: &[&str], default: LevelFilter) -> LoggingGuard {
+    LoggingConfig::new()
+        .set_level(level_from_envs(names, default))
+        .init()
+}
+
+/// Whether `finish_init` was called
+static INIT_FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// End the init phase of `LoggingConfig::set_init_level`, switching to the
+/// steady level<br><br>
+/// Only the first call has an effect. Records logged after it are filtered at
+/// the steady level; those logged before it keep the init level. Call it once
+/// startup is done, e.g. after the SDR device is opened and tuned.
+pub fn finish_init() {
+    if INIT_FINISHED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    if let Some(config) = CURRENT_CONFIG.get() {
+        log::set_max_level(config.max_level());
+    }
+}
+
+/// Whether the init phase of `LoggingConfig::set_init_level` is over
+pub(crate) fn init_finished() -> bool {
+    INIT_FINISHED.load(Ordering::Relaxed)
+}
+
+/// Restores the maximum level when dropped, also if the closure panics
+struct RestoreMaxLevel(LevelFilter);
+
+impl Drop for RestoreMaxLevel {
+    fn drop(&mut self) {
+        log::set_max_level(self.0);
+    }
+}
+
+/// Run `f` with logging turned off, restoring the level afterwards<br><br>
+/// Meant for quieting a noisy third party call. The `log` maximum level is
+/// process wide, so records from all threads are suppressed while `f` runs,
+/// not only those of the calling thread. Calls may be nested; overlapping
+/// calls from several threads can restore the levels out of order.
+/// `log_fatal_and_flush` is not affected.<br><br>
+/// `let devices = with_level_silenced(|| rtlsdr::list_devices());`
+pub fn with_level_silenced<R>(f: impl FnOnce() -> R) -> R {
+    let _restore = RestoreMaxLevel(log::max_level());
+    log::set_max_level(LevelFilter::Off);
+    f()
+}
+
+/// Build the logger writing records to `target` per `config`, filtered at
+/// its levels. `is_terminal` tells whether `target` is a terminal, for
+/// `ColorPreference::Auto`.
+fn sink_logger(config: LoggingConfig, target: Target, is_terminal: bool) -> Box<dyn log::Log> {
+    let gate_config = config.clone();
+    let logger = builder(config, target, is_terminal).build();
+    Box::new(force::LevelGate::new(Box::new(logger), &gate_config))
+}
+
+/// Build an env_logger `Builder` that formats records per `config` and writes
+/// them to `target`. `is_terminal` tells whether `target` is a terminal, for
+/// `ColorPreference::Auto`. The levels are left to `force::LevelGate`.
+fn builder(config: LoggingConfig, target: Target, is_terminal: bool) -> Builder {
+    // binary frames must reach the output untouched, so they are never run
+    // through the filter that strips color codes. A forced color preference
+    // keeps the codes even in files and pipes, e.g. for viewing with `less -R`.
+    let write_style = if config.format() == LogFormat::Binary || config.color().resolve(is_terminal)
+    {
+        WriteStyle::Always
+    } else {
+        WriteStyle::Never
+    };
+    let formatter = format::RecordFormatter::new(config.clone())
+        .with_colored(write_style == WriteStyle::Always);
+    let target = match formatter.latency() {
+        Some(latency) => Target::Pipe(match target {
+            Target::Stdout => Box::new(writer::TimedWriter::new(std::io::stdout(), latency)),
+            Target::Pipe(pipe) => Box::new(writer::TimedWriter::new(pipe, latency)),
+            _ => Box::new(writer::TimedWriter::new(std::io::stderr(), latency)),
+        }),
+        None => target,
+    };
+
+    let mut builder = Builder::new();
+    builder
+        .format(move |buf, record| {
+            // env_logger drops the record on an error, count it before it does
+            formatter.write(buf, record).map_err(|error| {
+                stats::count_write_error();
+                error
+            })
+        })
+        .filter(None, LevelFilter::Trace)
+        .write_style(write_style)
+        .target(target);
+    config.run_builder_hook(&mut builder);
+    builder
+}
+
+/// Flushes the output when dropped, returned by `LoggingConfig::init`<br><br>
+/// A run of repeats held back by `LoggingConfig::set_collapse_repeats` is
+/// written with its count. If `LoggingConfig::set_summary_on_drop` is
+/// enabled, a summary of the records logged is logged next, followed by the stop banner of
+/// `LoggingConfig::set_banners`. The guard of an `init` that did not install
+/// the logger, because one was already installed, only flushes.
+#[must_use = "dropping the guard ends logging setup; bind it with let _guard"]
+pub struct LoggingGuard {
+    /// Whether `init` installed the logger, so the guard owns the running one
+    installed: bool,
+    summary: bool,
+    stop_banner: Option<String>,
+    live_theme: Arc<color::LiveTheme>,
+    live_filter: Arc<filter::LiveFilter>,
+}
+
+impl LoggingGuard {
+    /// Switch the running logger to `theme`, e.g. to a high-contrast theme
+    /// chosen by an operator, without restarting<br><br>
+    /// Applies to the main output and the sinks from the next record on, and
+    /// to `level_token`. The theme is read once per record, behind a lock
+    /// that is only contended while it is being replaced, so the cost is a
+    /// few nanoseconds per record. Does nothing if `init` did not install the
+    /// logger.
+    pub fn set_theme(&self, theme: ColorTheme) {
+        self.live_theme.set_theme(theme);
+    }
+
+    /// Replace the module filter of the running logger, e.g. to trace one
+    /// module from an admin command and revert it later<br><br>
+    /// `guard.set_module_filter("receiver::rf=trace".parse()?);`<br><br>
+    /// The filter replaces the one of `LoggingConfig::set_module_filter` for
+    /// every output and sink from the next record on, and the `log` maximum
+    /// level follows it. As at init, a default level in the filter replaces
+    /// `LoggingConfig::set_level`, and without one targets matching no module
+    /// keep that level; revert with the filter given at init, or
+    /// `ModuleFilter::new()` if there was none. Each record reads the filter
+    /// behind a lock that is only contended while it is being replaced, and
+    /// scans its directives as before, so the cost grows with the number of
+    /// directives but not with the reloading. `current_config` keeps the
+    /// filter given at init. Does nothing if `init` did not install the
+    /// logger.
+    pub fn set_module_filter(&self, filter: ModuleFilter) {
+        if !self.installed {
+            return;
+        }
+        self.live_filter.set(filter);
+        if let Some(config) = CURRENT_CONFIG.get() {
+            log::set_max_level(config.max_level());
+        }
+    }
+
+    /// Turn the color of the running logger off, or back on<br><br>
+    /// Outputs that were not colored at init, per their `ColorPreference`,
+    /// stay uncolored. While the color is off, each record is formatted into
+    /// a buffer and its styling removed before the write, which costs a copy
+    /// of the line; with the color on, the switch is one atomic load per
+    /// record. Binary output is never changed.
+    pub fn set_color_enabled(&self, enabled: bool) {
+        self.live_theme.set_color(enabled);
+    }
+
+    /// Hold back the console output, e.g. while a prompt or a TUI frame owns
+    /// the terminal, until `resume`<br><br>
+    /// Records are still formatted when they are logged, so they keep their
+    /// timestamps, and up to `LoggingConfig::set_pause_capacity` of them are
+    /// held in memory; beyond that the oldest are dropped. Only stdout and
+    /// stderr are paused, log files and sinks writing to files keep being
+    /// written, as do targets set through `LoggingConfig::set_builder_hook`.
+    /// Dropping the guard resumes the console. Does nothing if `init` did not
+    /// install the logger.
+    pub fn pause(&self) {
+        if self.installed {
+            writer::PAUSE.pause();
+        }
+    }
+
+    /// End a `pause`, writing the held records or throwing them away per
+    /// `LoggingConfig::set_resume_policy`<br><br>
+    /// Records logged while the held ones are written wait for them. If
+    /// records were dropped or discarded, a line counting them is logged
+    /// after the held records. Does nothing if the console is not paused, or
+    /// if `init` did not install the logger.
+    pub fn resume(&self) {
+        if !self.installed {
+            return;
+        }
+        if let Some(notice) = writer::PAUSE.resume(writer::Stream::write_record) {
+            log::warn!(target: "sdre_rust_logging", "{notice}");
+        }
+    }
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if !self.installed {
+            flush_all();
+            return;
+        }
+        heartbeat::stop();
+        self.resume();
+        // forced past the levels, as the held back repeats passed them already
+        log_forced(log::Level::Error, format::FLUSH_TARGET, format_args!(""));
+        let logger = log::logger();
+        if self.summary {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("{}", stats::Stats::snapshot().summary()))
+                    .level(log::Level::Info)
+                    .target("sdre_rust_logging")
+                    .build(),
             );
-            writeln!(buf, "[{}][{}]{}", level, time, record.args())
+        }
+        if let Some(banner) = &self.stop_banner {
+            log_banner(banner);
+        }
+        flush_all();
+    }
+}
+
+/// Log `text` as a banner line, see `LoggingConfig::set_banners`
+fn log_banner(text: &str) {
+    log::logger().log(
+        &log::Record::builder()
+            .args(format_args!("{text}"))
+            .level(log::Level::Info)
+            .target(format::BANNER_TARGET)
+            .build(),
+    );
+}
+
+/// Flush the logger and the standard streams<br><br>
+/// `LoggingGuard` does this when it is dropped. Applications with their own
+/// signal handling should call this from their shutdown path, and use
+/// `flush_on_signals` (with the `signals` feature) only if they have none.
+pub fn flush_all() {
+    log::logger().flush();
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+/// Install the logger for `config`, returning whether it was installed
+fn set_builder(config: LoggingConfig) -> bool {
+    let (logger, file_errors) = build_logger(&config);
+    let installed = early::install(logger, config.max_level());
+    if installed {
+        let _ = CURRENT_CONFIG.set(Arc::new(config));
+        for error in file_errors {
+            log::warn!("{}", file_error_message(&error));
+        }
+    }
+    installed
+}
+
+/// The warning logged when the local UTC offset is further than `tolerance`
+/// from `expected`, see `LoggingConfig::set_expected_utc_offset`
+fn utc_offset_warning(
+    local: chrono::FixedOffset,
+    expected: chrono::FixedOffset,
+    tolerance: std::time::Duration,
+) -> Option<String> {
+    let difference = local.local_minus_utc().abs_diff(expected.local_minus_utc());
+    (u64::from(difference) > tolerance.as_secs()).then(|| {
+        format!("Local timezone is UTC{local}, expected UTC{expected}; timestamps of this host will be off")
+    })
+}
+
+/// The warning logged for a log file that could not be opened
+fn file_error_message(error: &str) -> String {
+    format!("Could not open log file {error}, logging to stderr instead")
+}
+
+/// The logger for `config` with its extra logger, without installing it, and
+/// the errors of the log files that could not be opened
+fn build_logger(config: &LoggingConfig) -> (Box<dyn log::Log>, Vec<String>) {
+    #[cfg(all(feature = "android", target_os = "android"))]
+    if let Some(tag) = config.android_tag() {
+        let logger = android::AndroidLogger::new(tag, config.clone());
+        return (with_extra_logger(Box::new(logger), config), Vec::new());
+    }
+
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    if config.browser_console() {
+        let logger = wasm::ConsoleLogger::new(config.clone());
+        return (with_extra_logger(Box::new(logger), config), Vec::new());
+    }
+
+    // one sink per distinct target, with the levels routed to it
+    let mut targets: Vec<&LogTarget> = Vec::new();
+    let mut routes = [0; 5];
+    for level in log::Level::iter() {
+        let target = config.level_target(level);
+        routes[level as usize - 1] = match targets.iter().position(|known| *known == target) {
+            Some(index) => index,
+            None => {
+                targets.push(target);
+                targets.len() - 1
+            }
+        };
+    }
+
+    let mut file_errors = Vec::new();
+    let mut sinks: Vec<Box<dyn log::Log>> = targets
+        .into_iter()
+        .map(|target| {
+            let (target, is_terminal) = open_target(target, config, &mut file_errors);
+            sink_logger(config.clone(), target, is_terminal)
         })
-        .filter(None, loglevel)
-        .try_init();
+        .collect();
+    let mut logger = if sinks.len() == 1 {
+        sinks.remove(0)
+    } else {
+        Box::new(route::RoutingLogger::new(sinks, routes))
+    };
+    for sink in config.sinks() {
+        let sink_config = config
+            .clone()
+            .set_format(sink.format())
+            .set_color(sink.color())
+            .set_show_detail(sink.show_detail())
+            .without_recent_lines();
+        let (target, is_terminal) = open_target(sink.target(), &sink_config, &mut file_errors);
+        let sink = route::SecondaryLogger::sink(sink_logger(sink_config, target, is_terminal));
+        logger = Box::new(route::TeeLogger::new(logger, Arc::new(sink)));
+    }
+
+    (with_extra_logger(logger, config), file_errors)
+}
+
+/// Build the logger for `config` without installing it, see `LoggingConfig::build`
+fn build_standalone(config: &LoggingConfig) -> Box<dyn log::Log> {
+    let (logger, file_errors) = build_logger(config);
+    for error in file_errors {
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("{}", file_error_message(&error)))
+                .level(log::Level::Warn)
+                .target("sdre_rust_logging")
+                .build(),
+        );
+    }
+    logger
+}
+
+/// Feed the records of `logger` to the extra logger of `config` as well, if set
+fn with_extra_logger(logger: Box<dyn log::Log>, config: &LoggingConfig) -> Box<dyn log::Log> {
+    match config.extra_logger() {
+        Some(extra) => {
+            let gated = force::LevelGate::new(Box::new(extra), config);
+            let extra = route::SecondaryLogger::user(Box::new(gated));
+            Box::new(route::TeeLogger::new(logger, Arc::new(extra)))
+        }
+        None => logger,
+    }
+}
+
+/// Written at the start of new log files with `set_utf8_bom`
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The target writing to `stream`, held back by `LoggingGuard::pause`, and
+/// whether it is a terminal
+fn console_target(stream: writer::Stream) -> (Target, bool) {
+    let is_terminal = match stream {
+        writer::Stream::Stdout => std::io::stdout().is_terminal(),
+        writer::Stream::Stderr => std::io::stderr().is_terminal(),
+    };
+    (
+        Target::Pipe(Box::new(writer::PauseWriter::new(stream))),
+        is_terminal,
+    )
+}
+
+/// The env_logger target for `target` and whether it is a terminal. A file
+/// that can not be opened falls back to stderr, with the error added to
+/// `file_errors`. Failed writes to a file are counted, and written to stderr
+/// with `set_write_error_fallback`. With `set_utf8_bom`, a file that is empty
+/// when it is opened starts with a byte order mark.
+fn open_target(
+    target: &LogTarget,
+    config: &LoggingConfig,
+    file_errors: &mut Vec<String>,
+) -> (Target, bool) {
+    match target {
+        LogTarget::Stderr => console_target(writer::Stream::Stderr),
+        LogTarget::Stdout => console_target(writer::Stream::Stdout),
+        LogTarget::File(path) => match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(mut file) => {
+                // binary frames must start the file, so it never gets a BOM
+                if config.utf8_bom()
+                    && config.format() != LogFormat::Binary
+                    && file.metadata().is_ok_and(|metadata| metadata.len() == 0)
+                    && file.write_all(UTF8_BOM).is_err()
+                {
+                    stats::count_write_error();
+                }
+                let fallback = config.write_error_fallback().then(std::io::stderr);
+                let writer = writer::FallbackWriter::new(file, fallback);
+                (Target::Pipe(Box::new(writer)), false)
+            }
+            Err(error) => {
+                file_errors.push(format!("{}: {error}", path.display()));
+                console_target(writer::Stream::Stderr)
+            }
+        },
+    }
 }
 
 impl SetupLogging for &str {
     fn set_logging_level(self) -> LevelFilter {
-        match self.to_lowercase().as_str() {
-            "error" => LevelFilter::Error,
-            "warn" => LevelFilter::Warn,
-            "info" => LevelFilter::Info,
-            "debug" => LevelFilter::Debug,
-            "trace" => LevelFilter::Trace,
-            _ => LevelFilter::Info,
-        }
+        self.set_logging_level_or(DEFAULT_LEVEL)
+    }
+
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter {
+        level_from_str_or(self, default)
     }
 
     fn enable_logging(&self) {
         let loglevel = self.set_logging_level();
-        set_builder(loglevel);
+        let _ = LoggingConfig::new().set_level(loglevel).init();
     }
 }
 
 impl SetupLogging for String {
     fn set_logging_level(self) -> LevelFilter {
-        match self.to_lowercase().as_str() {
-            "error" => LevelFilter::Error,
-            "warn" => LevelFilter::Warn,
-            "info" => LevelFilter::Info,
-            "debug" => LevelFilter::Debug,
-            "trace" => LevelFilter::Trace,
-            _ => LevelFilter::Info,
-        }
+        self.set_logging_level_or(DEFAULT_LEVEL)
+    }
+
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter {
+        level_from_str_or(&self, default)
     }
 
     fn enable_logging(&self) {
         // FIXME: this clone seems unnecessary
         let loglevel = self.clone().set_logging_level();
-        set_builder(loglevel);
+        let _ = LoggingConfig::new().set_level(loglevel).init();
     }
 }
 
 impl SetupLogging for usize {
     fn set_logging_level(self) -> LevelFilter {
-        match self {
-            1 => LevelFilter::Error,
-            2 => LevelFilter::Warn,
-            3 => LevelFilter::Info,
-            4 => LevelFilter::Debug,
-            5 => LevelFilter::Trace,
-            _ => LevelFilter::Info,
-        }
+        self.set_logging_level_or(DEFAULT_LEVEL)
+    }
+
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter {
+        level_from_int_or(self as u64, default)
     }
 
     fn enable_logging(&self) {
         let loglevel = self.set_logging_level();
-        set_builder(loglevel);
+        let _ = LoggingConfig::new().set_level(loglevel).init();
     }
 }
 
 impl SetupLogging for u8 {
     fn set_logging_level(self) -> LevelFilter {
-        match self {
-            1 => LevelFilter::Error,
-            2 => LevelFilter::Warn,
-            3 => LevelFilter::Info,
-            4 => LevelFilter::Debug,
-            5 => LevelFilter::Trace,
-            _ => LevelFilter::Info,
+        self.set_logging_level_or(DEFAULT_LEVEL)
+    }
+
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter {
+        level_from_int_or(u64::from(self), default)
+    }
+
+    fn enable_logging(&self) {
+        let loglevel = self.set_logging_level();
+        let _ = LoggingConfig::new().set_level(loglevel).init();
+    }
+}
+
+impl SetupLogging for bool {
+    fn set_logging_level(self) -> LevelFilter {
+        self.set_logging_level_or(DEFAULT_LEVEL)
+    }
+
+    /// For a `--verbose` flag: `true` is `LevelFilter::Debug`, `false` is the
+    /// default, `LevelFilter::Info` unless another one is passed
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter {
+        if self {
+            LevelFilter::Debug
+        } else {
+            default
         }
     }
 
     fn enable_logging(&self) {
         let loglevel = self.set_logging_level();
-        set_builder(loglevel);
+        let _ = LoggingConfig::new().set_level(loglevel).init();
+    }
+}
+
+impl<T: SetupLogging> SetupLogging for Option<T> {
+    fn set_logging_level(self) -> LevelFilter {
+        self.set_logging_level_or(DEFAULT_LEVEL)
+    }
+
+    /// `Some` values resolve through the inner type's implementation,
+    /// `None` resolves to the default, `LevelFilter::Info` unless another one is passed.
+    fn set_logging_level_or(self, default: LevelFilter) -> LevelFilter {
+        match self {
+            Some(level) => level.set_logging_level_or(default),
+            None => default,
+        }
+    }
+
+    fn enable_logging(&self) {
+        match self {
+            Some(level) => level.enable_logging(),
+            None => {
+                let _ = LoggingConfig::new().init();
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use log::Log;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer that can be read back after it was given to a logger
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    /// Log `message` at info through a logger built from `config`, writing to
+    /// a pipe that is not a terminal, and return the output
+    fn log_to_pipe(config: LoggingConfig, message: &str) -> String {
+        let buffer = SharedBuffer::default();
+        let logger = builder(config, Target::Pipe(Box::new(buffer.clone())), false).build();
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("{message}"))
+                .level(log::Level::Info)
+                .build(),
+        );
+        buffer.contents()
+    }
+
+    #[test]
+    fn test_forced_color_in_pipe() {
+        let colored = log_to_pipe(
+            LoggingConfig::new().set_color(ColorPreference::Always),
+            "message",
+        );
+        assert!(colored.contains("\x1b["));
+        assert!(colored.ends_with("]message\n"));
+
+        let plain = log_to_pipe(
+            LoggingConfig::new().set_color(ColorPreference::Never),
+            "message",
+        );
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.starts_with("[INFO ]["));
+    }
+
+    #[test]
+    fn test_module_filter() {
+        let config =
+            LoggingConfig::new().set_module_filter("receiver::rf=trace,hyper=off".parse().unwrap());
+        assert_eq!(config.max_level(), LevelFilter::Trace);
+        let logger = sink_logger(config, Target::Stderr, false);
+        let enabled = |target: &str, level: log::Level| {
+            logger.enabled(&log::Metadata::builder().target(target).level(level).build())
+        };
+        assert!(enabled("receiver::rf::tuner", log::Level::Trace));
+        assert!(!enabled("receiver::net", log::Level::Debug));
+        assert!(enabled("receiver::net", log::Level::Info));
+        assert!(!enabled("hyper::client", log::Level::Error));
+    }
+
+    #[test]
+    fn test_extra_logger_level() {
+        let buffer = SharedBuffer::default();
+        let extra = builder(
+            LoggingConfig::new(),
+            Target::Pipe(Box::new(buffer.clone())),
+            false,
+        )
+        .build();
+        let config = LoggingConfig::new()
+            .set_module_filter("receiver::rf=trace".parse().unwrap())
+            .set_extra_logger(Some(Arc::new(extra)));
+        let primary = builder(
+            LoggingConfig::new(),
+            Target::Pipe(Box::new(SharedBuffer::default())),
+            false,
+        )
+        .build();
+        let logger = with_extra_logger(Box::new(primary), &config);
+        for (target, level) in [
+            ("receiver::rf", log::Level::Trace),
+            ("receiver::net", log::Level::Debug),
+        ] {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("{target}"))
+                    .level(level)
+                    .target(target)
+                    .build(),
+            );
+        }
+        let output = buffer.contents();
+        assert!(output.ends_with("]receiver::rf\n"));
+        assert!(!output.contains("receiver::net"));
+    }
+
+    /// A writer that fails every write
+    struct Failing;
+
+    impl Write for Failing {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failing_sink() {
+        let buffer = SharedBuffer::default();
+        let failing = builder(LoggingConfig::new(), Target::Pipe(Box::new(Failing)), false).build();
+        let working = builder(
+            LoggingConfig::new(),
+            Target::Pipe(Box::new(buffer.clone())),
+            false,
+        )
+        .build();
+        let logger = route::TeeLogger::new(Box::new(failing), Arc::new(working));
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("still written"))
+                .level(log::Level::Info)
+                .build(),
+        );
+        assert!(buffer.contents().ends_with("]still written\n"));
+    }
+
+    #[test]
+    fn test_builder_hook() {
+        let config = LoggingConfig::new().set_builder_hook(|builder| {
+            builder.format(|buf, record| writeln!(buf, "custom: {}", record.args()));
+        });
+        assert_eq!(log_to_pipe(config, "message"), "custom: message\n");
+    }
+
+    #[test]
+    fn test_build_standalone() {
+        let buffer = SharedBuffer::default();
+        let pipe = buffer.clone();
+        let logger = LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_builder_hook(move |builder| {
+                builder.target(Target::Pipe(Box::new(pipe.clone())));
+            })
+            .build();
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("not installed"))
+                .level(log::Level::Info)
+                .build(),
+        );
+        assert!(buffer.contents().ends_with("]not installed\n"));
+    }
+
+    #[test]
+    fn test_utf8_bom() {
+        let path = std::env::temp_dir().join(format!("sdre-bom-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = LoggingConfig::new()
+            .set_target(LogTarget::File(path.clone()))
+            .set_utf8_bom(true);
+        let record = log::Record::builder()
+            .args(format_args!("décodé"))
+            .level(log::Level::Info)
+            .build();
+        // the second logger appends to the file the first one started
+        for _ in 0..2 {
+            let logger = config.clone().build();
+            logger.log(&record);
+            logger.log(&record);
+            logger.flush();
+        }
+        let output = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(output.starts_with(UTF8_BOM));
+        let text = String::from_utf8(output[UTF8_BOM.len()..].to_vec()).unwrap();
+        assert!(!text.contains('\u{feff}'));
+        assert_eq!(text.lines().count(), 4);
+    }
 
     #[test]
     fn test_set_logging_level() {
-        let info_level: u8 = 0;
-        let debug_level: u8 = 1;
-        let trace_level: u8 = 2;
+        let info_level: u8 = 3;
+        let debug_level: u8 = 4;
+        let trace_level: u8 = 5;
         let stupid_levels: u8 = 255;
         let info_level_logging: LevelFilter = info_level.set_logging_level();
         let debug_level_logging: LevelFilter = debug_level.set_logging_level();
@@ -173,6 +916,124 @@ mod test {
         assert_eq!(info_level_logging, LevelFilter::Info);
         assert_eq!(debug_level_logging, LevelFilter::Debug);
         assert_eq!(trace_level_logging, LevelFilter::Trace);
-        assert_eq!(stupid_levels_logging, LevelFilter::Trace);
+        assert_eq!(stupid_levels_logging, LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_current_config() {
+        let _guard = LoggingConfig::new().set_level(LevelFilter::Debug).init();
+        let config = current_config().unwrap();
+        assert_eq!(config.level(), LevelFilter::Debug);
+
+        // a second init does not replace the installed logger or its config
+        let _ = LoggingConfig::new().set_level(LevelFilter::Error).init();
+        assert!(Arc::ptr_eq(&config, &current_config().unwrap()));
+    }
+
+    #[test]
+    fn test_level_token() {
+        let (style, text) = level_token(log::Level::Warn);
+        assert_eq!(text, "WARN ");
+        assert_eq!(style, ColorTheme::default().warn);
+        assert_eq!(
+            styled_level(log::Level::Warn),
+            format!("{style}WARN {style:#}")
+        );
+    }
+
+    #[test]
+    fn test_with_level_silenced() {
+        let _ = LoggingConfig::new().set_level(LevelFilter::Debug).init();
+        let before = log::max_level();
+        let result = with_level_silenced(|| {
+            assert_eq!(log::max_level(), LevelFilter::Off);
+            with_level_silenced(log::max_level)
+        });
+        assert_eq!(result, LevelFilter::Off);
+        assert_eq!(log::max_level(), before);
+    }
+
+    #[test]
+    fn test_logging_level_or() {
+        assert_eq!(
+            "loud".set_logging_level_or(LevelFilter::Warn),
+            LevelFilter::Warn
+        );
+        assert_eq!(
+            String::from("debug").set_logging_level_or(LevelFilter::Warn),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            9u8.set_logging_level_or(LevelFilter::Error),
+            LevelFilter::Error
+        );
+        assert_eq!(
+            false.set_logging_level_or(LevelFilter::Warn),
+            LevelFilter::Warn
+        );
+        assert_eq!(
+            None::<u8>.set_logging_level_or(LevelFilter::Trace),
+            LevelFilter::Trace
+        );
+        assert_eq!(
+            Some(7usize).set_logging_level_or(LevelFilter::Off),
+            LevelFilter::Off
+        );
+    }
+
+    /// A type implementing only the methods the trait required before
+    /// `set_logging_level_or` was added
+    struct Quiet;
+
+    impl SetupLogging for Quiet {
+        fn set_logging_level(self) -> LevelFilter {
+            LevelFilter::Error
+        }
+
+        fn enable_logging(&self) {}
+    }
+
+    #[test]
+    fn test_logging_level_or_default() {
+        assert_eq!(
+            Quiet.set_logging_level_or(LevelFilter::Warn),
+            LevelFilter::Error
+        );
+        assert_eq!(
+            Some(Quiet).set_logging_level_or(LevelFilter::Warn),
+            LevelFilter::Error
+        );
+    }
+
+    #[test]
+    fn test_bool_logging_level() {
+        assert_eq!(true.set_logging_level(), LevelFilter::Debug);
+        assert_eq!(false.set_logging_level(), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_option_logging_level() {
+        let some_level: Option<&str> = Some("debug");
+        let some_number: Option<u8> = Some(1);
+        let no_level: Option<String> = None;
+        assert_eq!(some_level.set_logging_level(), LevelFilter::Debug);
+        assert_eq!(some_number.set_logging_level(), LevelFilter::Error);
+        assert_eq!(no_level.set_logging_level(), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_utc_offset_warning() {
+        let utc = chrono::FixedOffset::east_opt(0).unwrap();
+        let cest = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        let hour = std::time::Duration::from_secs(3600);
+        assert_eq!(
+            utc_offset_warning(utc, utc, std::time::Duration::ZERO),
+            None
+        );
+        assert_eq!(
+            utc_offset_warning(cest, utc, hour).as_deref(),
+            Some("Local timezone is UTC+02:00, expected UTC+00:00; timestamps of this host will be off")
+        );
+        assert_eq!(utc_offset_warning(utc, cest, 2 * hour), None);
     }
 }
@@ -0,0 +1,88 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Panics logged as error records, for `log_panics`
+
+use log::{Level, Record};
+use std::any::Any;
+use std::panic;
+use std::thread;
+
+/// The message of a panic payload, as the standard library's hook prints it
+fn payload_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+/// Log every panic as an error record, so a worker thread that panics shows
+/// up in the log stream and reaches every output and sink<br><br>
+/// `log_panics();` after `init` logs e.g.<br>
+/// \[ERROR\]\[2021-08-22T15:49:01\]Thread 'decoder' panicked: index out of bounds location=src/decode.rs:42:17<br><br>
+/// The record is logged from the `panic` target with `location` and `thread`
+/// key-values, then the output is flushed. The hook that was installed before
+/// runs afterwards, so hooks of other crates keep working; with the standard
+/// library's default hook, the panic is also printed to stderr as usual.
+/// Install an empty hook first, `std::panic::set_hook(Box::new(|_| {}))`, to
+/// only get the record.<br><br>
+/// The hook runs before unwinding starts and also with `panic = "abort"`,
+/// so the record is written even when the process aborts right after it.
+/// An abort skips the `LoggingGuard` though: the summary and the stop banner
+/// are not written, and records held by `LoggingGuard::pause` are lost.
+pub fn log_panics() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let location = info.location().map(ToString::to_string);
+        let thread = thread::current();
+        let name = thread.name().unwrap_or("<unnamed>");
+        let mut fields = vec![("thread", name)];
+        if let Some(location) = &location {
+            fields.insert(0, ("location", location.as_str()));
+        }
+        log::logger().log(
+            &Record::builder()
+                .args(format_args!(
+                    "Thread '{name}' panicked: {}",
+                    payload_message(info.payload())
+                ))
+                .level(Level::Error)
+                .target("panic")
+                .key_values(&fields)
+                .build(),
+        );
+        crate::flush_all();
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_payload_message() {
+        assert_eq!(payload_message(&"static message"), "static message");
+        assert_eq!(
+            payload_message(&String::from("formatted 42")),
+            "formatted 42"
+        );
+        assert_eq!(payload_message(&42), "Box<dyn Any>");
+    }
+}
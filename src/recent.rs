@@ -0,0 +1,103 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! The buffer of the most recent lines of `LoggingConfig::set_recent_log_capacity`,
+//! for applications that dump them on demand, e.g. into a bug report
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The lines of the logger `init` installed, set once it was installed
+static RECENT: OnceLock<Arc<RecentLines>> = OnceLock::new();
+
+/// The most recent lines written since `init`, oldest first, without styling
+/// or line breaks<br><br>
+/// `for line in recent_logs() { eprintln!("{line}"); }`<br><br>
+/// Empty unless `LoggingConfig::set_recent_log_capacity` is set. The lines are
+/// the ones the main output wrote, so they can be pasted into a bug report as
+/// they are.
+#[must_use]
+pub fn recent_logs() -> Vec<String> {
+    RECENT.get().map_or_else(Vec::new, |recent| recent.lines())
+}
+
+/// Make `recent` the lines of `recent_logs`, once `init` installed the logger
+/// writing them
+pub(crate) fn publish(recent: Arc<RecentLines>) {
+    let _ = RECENT.set(recent);
+}
+
+/// The most recent lines of the outputs, oldest first, and how many are kept
+#[derive(Debug)]
+pub(crate) struct RecentLines {
+    lines: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl RecentLines {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::new()),
+            capacity,
+        }
+    }
+
+    /// Add the lines of `text`, dropping the oldest beyond the capacity
+    pub(crate) fn push(&self, text: &str) {
+        let mut lines = self
+            .lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for line in text.lines() {
+            if lines.len() == self.capacity {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recent_push() {
+        let recent = RecentLines::new(3);
+        recent.push("[INFO ]tuned\n");
+        recent.push("[WARN ]one\ntwo\n");
+        assert_eq!(recent.lines(), ["[INFO ]tuned", "[WARN ]one", "two"]);
+        recent.push("[ERROR]lost\n");
+        assert_eq!(recent.lines(), ["[WARN ]one", "two", "[ERROR]lost"]);
+    }
+
+    #[test]
+    fn test_recent_unbounded() {
+        // the memory grows with the lines kept, not with the capacity
+        let recent = RecentLines::new(usize::MAX);
+        recent.push("[INFO ]tuned\n");
+        assert_eq!(recent.lines(), ["[INFO ]tuned"]);
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Routing of records to a sink per level, for `LoggingConfig::set_level_target`,
+//! and to the sinks of `LoggingConfig::add_sink` and the user's logger of
+//! `LoggingConfig::set_extra_logger`
+
+use log::{Log, Metadata, Record};
+use std::sync::Arc;
+
+/// Logger forwarding each record to the sink of its level
+pub(crate) struct RoutingLogger {
+    sinks: Vec<Box<dyn Log>>,
+    /// Index into `sinks` per level, indexed by `Level as usize - 1`
+    routes: [usize; 5],
+}
+
+impl RoutingLogger {
+    pub(crate) fn new(sinks: Vec<Box<dyn Log>>, routes: [usize; 5]) -> Self {
+        Self { sinks, routes }
+    }
+
+    fn sink(&self, metadata: &Metadata) -> &dyn Log {
+        self.sinks[self.routes[metadata.level() as usize - 1]].as_ref()
+    }
+}
+
+impl Log for RoutingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.sink(metadata).enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        // every sink may hold back repeats
+        if record.target() == crate::format::FLUSH_TARGET {
+            for sink in &self.sinks {
+                sink.log(record);
+            }
+            return;
+        }
+        self.sink(record.metadata()).log(record);
+    }
+
+    fn flush(&self) {
+        for sink in &self.sinks {
+            sink.flush();
+        }
+    }
+}
+
+/// A sink of `LoggingConfig::add_sink` or the user's logger of
+/// `LoggingConfig::set_extra_logger`, given records the outputs already
+/// counted and numbered, see `format::as_secondary`
+pub(crate) struct SecondaryLogger {
+    inner: Box<dyn Log>,
+    /// Whether the record of `format::FLUSH_TARGET` is passed on, which only
+    /// the formatters of this crate understand
+    flush: bool,
+}
+
+impl SecondaryLogger {
+    /// A sink of `LoggingConfig::add_sink`
+    pub(crate) fn sink(inner: Box<dyn Log>) -> Self {
+        Self { inner, flush: true }
+    }
+
+    /// The user's logger of `LoggingConfig::set_extra_logger`
+    pub(crate) fn user(inner: Box<dyn Log>) -> Self {
+        Self {
+            inner,
+            flush: false,
+        }
+    }
+}
+
+impl Log for SecondaryLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.flush && record.target() == crate::format::FLUSH_TARGET {
+            return;
+        }
+        crate::format::as_secondary(|| self.inner.log(record));
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Logger passing each record to this crate's output, then to an extra sink
+/// or the user's logger
+pub(crate) struct TeeLogger {
+    primary: Box<dyn Log>,
+    extra: Arc<dyn Log>,
+}
+
+impl TeeLogger {
+    pub(crate) fn new(primary: Box<dyn Log>, extra: Arc<dyn Log>) -> Self {
+        Self { primary, extra }
+    }
+}
+
+impl Log for TeeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.primary.enabled(metadata) || self.extra.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.primary.log(record);
+        if self.extra.enabled(record.metadata()) {
+            self.extra.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.primary.flush();
+        self.extra.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use log::Level;
+    use std::sync::{Arc, Mutex};
+
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Collect {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push(format!("{}", record.args()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_tee() {
+        let primary = Arc::new(Mutex::new(Vec::new()));
+        let extra = Arc::new(Mutex::new(Vec::new()));
+        let logger = TeeLogger::new(
+            Box::new(Collect(primary.clone())),
+            Arc::new(Collect(extra.clone())),
+        );
+        logger.log(
+            &Record::builder()
+                .args(format_args!("tuned"))
+                .level(Level::Info)
+                .build(),
+        );
+        assert_eq!(*primary.lock().unwrap(), ["tuned"]);
+        assert_eq!(*extra.lock().unwrap(), ["tuned"]);
+    }
+
+    #[test]
+    fn test_routing() {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let rest = Arc::new(Mutex::new(Vec::new()));
+        let logger = RoutingLogger::new(
+            vec![
+                Box::new(Collect(rest.clone())),
+                Box::new(Collect(errors.clone())),
+            ],
+            [1, 1, 0, 0, 0],
+        );
+        for (level, message) in [
+            (Level::Info, "tuned"),
+            (Level::Error, "lost"),
+            (Level::Warn, "weak"),
+            (Level::Trace, "sample"),
+        ] {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("{message}"))
+                    .level(level)
+                    .build(),
+            );
+        }
+        assert_eq!(*errors.lock().unwrap(), ["lost", "weak"]);
+        assert_eq!(*rest.lock().unwrap(), ["tuned", "sample"]);
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Logging `serde::Serialize` values as key-values, with the `serde` feature
+
+use log::kv::{self, Key, Source, Value, VisitSource};
+use log::{Level, Metadata, Record};
+use serde::Serialize;
+use std::fmt;
+
+/// A field value converted from JSON
+enum FieldValue {
+    Null,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Str(String),
+}
+
+impl FieldValue {
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            FieldValue::Null => Value::null(),
+            FieldValue::Bool(value) => Value::from(*value),
+            FieldValue::U64(value) => Value::from(*value),
+            FieldValue::I64(value) => Value::from(*value),
+            FieldValue::F64(value) => Value::from(*value),
+            FieldValue::Str(value) => Value::from(value.as_str()),
+        }
+    }
+}
+
+impl From<serde_json::Value> for FieldValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => FieldValue::Null,
+            serde_json::Value::Bool(value) => FieldValue::Bool(value),
+            serde_json::Value::Number(number) => {
+                if let Some(value) = number.as_u64() {
+                    FieldValue::U64(value)
+                } else if let Some(value) = number.as_i64() {
+                    FieldValue::I64(value)
+                } else {
+                    FieldValue::F64(number.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            serde_json::Value::String(value) => FieldValue::Str(value),
+            // nested values are kept as their JSON text, so the fields stay flat
+            nested => FieldValue::Str(nested.to_string()),
+        }
+    }
+}
+
+/// The top level fields of a serialized value
+struct SerializedFields(Vec<(String, FieldValue)>);
+
+impl SerializedFields {
+    fn new<T: Serialize + ?Sized>(value: &T) -> Self {
+        let fields = match serde_json::to_value(value) {
+            Ok(serde_json::Value::Object(map)) => map
+                .into_iter()
+                .map(|(key, value)| (key, FieldValue::from(value)))
+                .collect(),
+            Ok(value) => vec![(String::from("value"), FieldValue::from(value))],
+            Err(error) => vec![(
+                String::from("serialize_error"),
+                FieldValue::Str(error.to_string()),
+            )],
+        };
+
+        Self(fields)
+    }
+}
+
+impl Source for SerializedFields {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), kv::Error> {
+        for (key, value) in &self.0 {
+            visitor.visit_pair(Key::from_str(key), value.to_value())?;
+        }
+        Ok(())
+    }
+}
+
+/// Log `value` as the key-values of a record<br><br>
+/// Each top level field of `value` becomes a key-value: `key=value` pairs after
+/// the message in `LogFormat::Human`, members of the object in `LogFormat::Json`.
+/// Nested structs, maps and sequences are written as their JSON text. A value
+/// that is not a struct or map is logged as a single `value` field. Usually
+/// called through the `log_serialized!` macro.
+pub fn log_serialize<T: Serialize + ?Sized>(
+    level: Level,
+    target: &str,
+    value: &T,
+    args: fmt::Arguments,
+) {
+    let metadata = Metadata::builder().level(level).target(target).build();
+    let logger = log::logger();
+    if level > log::max_level() || !logger.enabled(&metadata) {
+        return;
+    }
+
+    let fields = SerializedFields::new(value);
+    logger.log(
+        &Record::builder()
+            .metadata(metadata)
+            .args(args)
+            .key_values(&fields)
+            .build(),
+    );
+}
+
+/// Log a `serde::Serialize` value as key-values, see `log_serialize`<br><br>
+/// `log_serialized!(Level::Info, position, "position update for {}", position.icao);`
+#[macro_export]
+macro_rules! log_serialized {
+    ($level:expr, $value:expr, $($arg:tt)+) => {
+        $crate::log_serialize($level, module_path!(), &$value, format_args!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fields::{human_fields, json_fields};
+
+    #[derive(Serialize)]
+    struct AircraftPosition {
+        icao: &'static str,
+        altitude: i64,
+        latitude: f64,
+        on_ground: bool,
+        squawk: Option<u16>,
+        history: Vec<u32>,
+    }
+
+    #[test]
+    fn test_serialized_fields() {
+        let position = AircraftPosition {
+            icao: "a1b2c3",
+            altitude: -200,
+            latitude: 51.5,
+            on_ground: false,
+            squawk: None,
+            history: vec![1, 2],
+        };
+        let fields = SerializedFields::new(&position);
+
+        assert_eq!(
+            human_fields(&fields),
+            " icao=a1b2c3 altitude=-200 latitude=51.5 on_ground=false squawk=None history=[1,2]"
+        );
+        assert_eq!(
+            String::from_utf8(json_fields(&fields)).unwrap(),
+            ",\"icao\":\"a1b2c3\",\"altitude\":-200,\"latitude\":51.5,\"on_ground\":false,\"squawk\":null,\"history\":\"[1,2]\""
+        );
+
+        let scalar = SerializedFields::new(&42);
+        assert_eq!(human_fields(&scalar), " value=42");
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Flushing the output on shutdown signals, with the `signals` feature
+
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use signal_hook::low_level::emulate_default_handler;
+use std::io;
+use std::thread;
+
+/// Flush the output when the process gets SIGTERM, SIGINT or SIGHUP<br><br>
+/// Starts a thread that waits for the signals, calls `flush_all` and then
+/// performs the default action of the signal, which terminates the process.
+/// The handlers replace the default action, so this must not be used by
+/// applications that handle these signals themselves, e.g. to reload on
+/// SIGHUP; they should call `flush_all` from their own handler instead.
+/// Unix only.
+pub fn flush_on_signals() -> io::Result<()> {
+    let mut signals = Signals::new([SIGTERM, SIGINT, SIGHUP])?;
+    thread::Builder::new()
+        .name(String::from("sdre-rust-logging-signals"))
+        .spawn(move || {
+            for signal in signals.forever() {
+                crate::flush_all();
+                let _ = emulate_default_handler(signal);
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,209 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! A whole configuration in one string, for a single environment variable
+
+use crate::{
+    ColorPreference, ColorTheme, LogFormat, LogTarget, LoggingConfig, LoggingGuard, ModuleFilter,
+    TimezoneSuffix,
+};
+use log::LevelFilter;
+use std::fmt;
+use std::str::FromStr;
+
+/// A logging spec that could not be parsed, see `enable_logging_spec`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError(String);
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid logging spec: {}", self.0)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// Parse `spec` as `enable_logging_spec` does and initialize logging with it<br><br>
+/// `let _guard = enable_logging_spec(&std::env::var("APP_LOG").unwrap_or_default())?;`<br><br>
+/// The spec is a list of tokens separated by `;`, e.g.
+/// `"info;utc;json;target=stderr"`. Tokens are case insensitive, surrounding
+/// spaces and empty tokens are ignored, and a later token replaces an
+/// earlier one of the same kind. An empty spec is `LoggingConfig::new()`.
+/// - a level name, including `off`, or `level=<level>`: `set_level`
+/// - `local`, `utc` or `offset`: local timestamps, `set_utc_timestamps` or
+///   local timestamps with `TimezoneSuffix::Offset`
+/// - `human`, `json`, `csv`, `binary`, `columns`, `commonlog` or `legacy`, or
+///   `format=<format>`: `set_format`
+/// - `target=stderr`, `target=stdout` or `target=<path>` for a file
+/// - `color=auto`, `color=always` or `color=never`: `set_color`
+/// - `theme=<name>`: a theme of `ColorTheme::named`
+/// - `filter=<directives>`: a `ModuleFilter`, e.g. `filter=warn,receiver=trace`
+///
+/// The path of a file target keeps its case.
+///
+/// # Errors
+/// A `SpecError` naming the first unknown token or invalid value. Logging is
+/// not initialized then.
+#[must_use = "dropping the guard ends logging setup; bind it with let _guard"]
+pub fn enable_logging_spec(spec: &str) -> Result<LoggingGuard, SpecError> {
+    Ok(spec.parse::<LoggingConfig>()?.init())
+}
+
+impl FromStr for LoggingConfig {
+    type Err = SpecError;
+
+    /// Parse a logging spec, see `enable_logging_spec` for the grammar
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = LoggingConfig::new();
+        for token in spec.split(';').map(str::trim).filter(|t| !t.is_empty()) {
+            config = match token.split_once('=') {
+                Some((key, value)) => apply_setting(config, key.trim(), value.trim(), token)?,
+                None => apply_word(config, token)
+                    .ok_or_else(|| SpecError(format!("unknown token `{token}`")))?,
+            };
+        }
+        Ok(config)
+    }
+}
+
+/// Apply a bare token, or `None` if it is not a level, timezone or format
+fn apply_word(config: LoggingConfig, word: &str) -> Option<LoggingConfig> {
+    if let Ok(level) = word.parse::<LevelFilter>() {
+        return Some(config.set_level(level));
+    }
+    match word.to_lowercase().as_str() {
+        "local" => Some(
+            config
+                .set_utc_timestamps(false)
+                .set_timezone_suffix(TimezoneSuffix::None),
+        ),
+        "utc" => Some(config.set_utc_timestamps(true)),
+        "offset" => Some(
+            config
+                .set_utc_timestamps(false)
+                .set_timezone_suffix(TimezoneSuffix::Offset),
+        ),
+        word => parse_format(word).map(|format| config.set_format(format)),
+    }
+}
+
+fn parse_format(name: &str) -> Option<LogFormat> {
+    match name {
+        "human" => Some(LogFormat::Human),
+        "json" => Some(LogFormat::Json),
+        "csv" => Some(LogFormat::Csv),
+        "binary" => Some(LogFormat::Binary),
+        "columns" => Some(LogFormat::Columns),
+        "commonlog" => Some(LogFormat::CommonLog),
+        "legacy" => Some(LogFormat::LegacyV1),
+        _ => None,
+    }
+}
+
+/// Apply a `key=value` token
+fn apply_setting(
+    config: LoggingConfig,
+    key: &str,
+    value: &str,
+    token: &str,
+) -> Result<LoggingConfig, SpecError> {
+    let invalid = || SpecError(format!("invalid value in `{token}`"));
+    Ok(match key.to_lowercase().as_str() {
+        "level" => config.set_level(value.parse().map_err(|_| invalid())?),
+        "format" => config.set_format(parse_format(&value.to_lowercase()).ok_or_else(invalid)?),
+        "target" => config.set_target(match value.to_lowercase().as_str() {
+            "" => return Err(invalid()),
+            "stderr" => LogTarget::Stderr,
+            "stdout" => LogTarget::Stdout,
+            _ => LogTarget::File(value.into()),
+        }),
+        "color" => config.set_color(match value.to_lowercase().as_str() {
+            "auto" => ColorPreference::Auto,
+            "always" => ColorPreference::Always,
+            "never" => ColorPreference::Never,
+            _ => return Err(invalid()),
+        }),
+        "theme" => config.set_theme(ColorTheme::named(value).map_err(|_| invalid())?),
+        "filter" => config.set_module_filter(value.parse::<ModuleFilter>().map_err(|_| invalid())?),
+        _ => return Err(SpecError(format!("unknown setting `{key}` in `{token}`"))),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let config: LoggingConfig = " info; UTC ;json;;target=stderr".parse().unwrap();
+        assert_eq!(config.level(), LevelFilter::Info);
+        assert!(config.utc_timestamps());
+        assert_eq!(config.format(), LogFormat::Json);
+        assert_eq!(config.target(), &LogTarget::Stderr);
+
+        let config: LoggingConfig = "level=off;offset;format=csv;target=Logs/app.csv;color=never"
+            .parse()
+            .unwrap();
+        assert_eq!(config.level(), LevelFilter::Off);
+        assert!(!config.utc_timestamps());
+        assert_eq!(config.timezone_suffix(), TimezoneSuffix::Offset);
+        assert_eq!(config.format(), LogFormat::Csv);
+        assert_eq!(config.target(), &LogTarget::File("Logs/app.csv".into()));
+        assert_eq!(config.color(), ColorPreference::Never);
+
+        let config: LoggingConfig = "debug;trace;filter=warn,receiver=trace;theme=monochrome"
+            .parse()
+            .unwrap();
+        assert_eq!(config.level(), LevelFilter::Trace);
+        assert_eq!(
+            config.module_filter(),
+            &"warn,receiver=trace".parse::<ModuleFilter>().unwrap()
+        );
+        assert_eq!(config.theme(), &ColorTheme::named("monochrome").unwrap());
+
+        assert_eq!(
+            "".parse::<LoggingConfig>().unwrap().format(),
+            LogFormat::Human
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_errors() {
+        assert_eq!(
+            "info;loud".parse::<LoggingConfig>().unwrap_err(),
+            SpecError(String::from("unknown token `loud`"))
+        );
+        assert_eq!(
+            "level=loud".parse::<LoggingConfig>().unwrap_err(),
+            SpecError(String::from("invalid value in `level=loud`"))
+        );
+        assert_eq!(
+            "format=xml"
+                .parse::<LoggingConfig>()
+                .unwrap_err()
+                .to_string(),
+            "invalid logging spec: invalid value in `format=xml`"
+        );
+        assert!("target=".parse::<LoggingConfig>().is_err());
+        assert!("color=sometimes".parse::<LoggingConfig>().is_err());
+        assert!("theme=neon".parse::<LoggingConfig>().is_err());
+        assert!("filter==trace".parse::<LoggingConfig>().is_err());
+        assert_eq!(
+            "volume=11".parse::<LoggingConfig>().unwrap_err(),
+            SpecError(String::from("unknown setting `volume` in `volume=11`"))
+        );
+    }
+}
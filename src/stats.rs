@@ -0,0 +1,189 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Process wide counters of what the logger has done
+
+use log::Level;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+static LEVEL_COUNTS: [AtomicU64; 5] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+static COLLAPSED: AtomicU64 = AtomicU64::new(0);
+static SAMPLED_OUT: AtomicU64 = AtomicU64::new(0);
+static FILTERED: AtomicU64 = AtomicU64::new(0);
+static WRITE_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// Count a record that reached the formatter
+pub(crate) fn count_record(level: Level) {
+    LEVEL_COUNTS[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "metrics")]
+    metrics::counter!(RECORDS_METRIC, "level" => metric_level(level)).increment(1);
+}
+
+/// Name of the counter of written records, with a `level` label
+#[cfg(feature = "metrics")]
+const RECORDS_METRIC: &str = "log_records_total";
+
+/// The `level` label value of `level`
+#[cfg(feature = "metrics")]
+fn metric_level(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warn",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// Count a repeated line that was held back by `set_collapse_repeats`
+pub(crate) fn count_collapsed() {
+    COLLAPSED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a record dropped by `set_sample_rate`
+pub(crate) fn count_sampled_out() {
+    SAMPLED_OUT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a record dropped by the message content filters
+pub(crate) fn count_filtered() {
+    FILTERED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a failed write or format of a record
+pub(crate) fn count_write_error() {
+    WRITE_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A snapshot of the counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Stats {
+    /// Records per level, indexed by `Level as usize - 1`
+    pub(crate) levels: [u64; 5],
+    pub(crate) collapsed: u64,
+    pub(crate) sampled_out: u64,
+    pub(crate) filtered: u64,
+    pub(crate) write_errors: u64,
+}
+
+impl Stats {
+    pub(crate) fn snapshot() -> Self {
+        Self {
+            levels: std::array::from_fn(|index| LEVEL_COUNTS[index].load(Ordering::Relaxed)),
+            collapsed: COLLAPSED.load(Ordering::Relaxed),
+            sampled_out: SAMPLED_OUT.load(Ordering::Relaxed),
+            filtered: FILTERED.load(Ordering::Relaxed),
+            write_errors: WRITE_ERRORS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// One line summary of the counters
+    pub(crate) fn summary(&self) -> String {
+        let mut summary = format!(
+            "Logged {} error, {} warn, {} info, {} debug, {} trace records",
+            self.levels[0], self.levels[1], self.levels[2], self.levels[3], self.levels[4]
+        );
+        if self.collapsed > 0 {
+            summary.push_str(&format!(", collapsed {} repeats", self.collapsed));
+        }
+        if self.sampled_out > 0 {
+            summary.push_str(&format!(", sampled out {}", self.sampled_out));
+        }
+        if self.filtered > 0 {
+            summary.push_str(&format!(", filtered {}", self.filtered));
+        }
+        if self.write_errors > 0 {
+            summary.push_str(&format!(", {} failed writes", self.write_errors));
+        }
+        summary
+    }
+
+    /// Summary of the records not written since `earlier`, or `None` if there were none
+    pub(crate) fn dropped_since(&self, earlier: &Self, interval: Duration) -> Option<String> {
+        let sampled_out = self.sampled_out - earlier.sampled_out;
+        let filtered = self.filtered - earlier.filtered;
+        let collapsed = self.collapsed - earlier.collapsed;
+        if sampled_out + filtered + collapsed == 0 {
+            return None;
+        }
+        Some(format!(
+            "Not written in the last {}s: {sampled_out} sampled out, {filtered} filtered, {collapsed} collapsed repeats",
+            interval.as_secs()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let stats = Stats {
+            levels: [1, 2, 3, 4, 5],
+            ..Stats::default()
+        };
+        assert_eq!(
+            stats.summary(),
+            "Logged 1 error, 2 warn, 3 info, 4 debug, 5 trace records"
+        );
+
+        let stats = Stats {
+            collapsed: 7,
+            ..stats
+        };
+        assert!(stats.summary().ends_with(", collapsed 7 repeats"));
+
+        let stats = Stats {
+            write_errors: 2,
+            ..stats
+        };
+        assert!(stats
+            .summary()
+            .ends_with(", collapsed 7 repeats, 2 failed writes"));
+    }
+
+    #[test]
+    fn test_dropped_since() {
+        let earlier = Stats {
+            sampled_out: 10,
+            ..Stats::default()
+        };
+        assert_eq!(
+            earlier.dropped_since(&earlier, Duration::from_secs(60)),
+            None
+        );
+
+        let now = Stats {
+            sampled_out: 130,
+            filtered: 4,
+            collapsed: 9,
+            ..Stats::default()
+        };
+        assert_eq!(
+            now.dropped_since(&earlier, Duration::from_secs(60))
+                .unwrap(),
+            "Not written in the last 60s: 120 sampled out, 4 filtered, 9 collapsed repeats"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Logger writing to the browser console, for wasm32 builds with the `wasm` feature
+
+use crate::format::{strip_styles, RecordFormatter};
+use crate::LoggingConfig;
+use log::{Level, Log, Metadata, Record};
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+pub(crate) struct ConsoleLogger {
+    config: LoggingConfig,
+    formatter: RecordFormatter,
+}
+
+impl ConsoleLogger {
+    pub(crate) fn new(config: LoggingConfig) -> Self {
+        Self {
+            formatter: RecordFormatter::new(config.clone()),
+            config,
+        }
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        crate::force::enabled(&self.config, metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut line = Vec::new();
+        if self.formatter.write(&mut line, record).is_err() || line.is_empty() {
+            return;
+        }
+        // the console shows ANSI codes literally, and adds its own line breaks
+        let line = strip_styles(&String::from_utf8_lossy(&line));
+        let line = JsValue::from_str(line.trim_end_matches('\n'));
+
+        // console.trace would add a stack trace to every record, so trace
+        // records are logged as debug
+        match record.level() {
+            Level::Error => console::error_1(&line),
+            Level::Warn => console::warn_1(&line),
+            Level::Info => console::info_1(&line),
+            Level::Debug | Level::Trace => console::debug_1(&line),
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,428 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! The writer of file outputs, which counts failed writes instead of
+//! returning them to env_logger, optionally writing the lines to stderr, the
+//! writer timing every output for `set_write_latency_interval` and the console
+//! writer held back by `LoggingGuard::pause`
+
+use crate::{stats, ResumePolicy};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The most write times kept per status interval, bounding the memory used
+/// under a log storm. Later writes are counted but not timed.
+const MAX_LATENCY_SAMPLES: usize = 100_000;
+
+/// Writes to `inner`, counting failed writes and passing the bytes to
+/// `fallback` instead, if set. Never returns an error, except interruptions
+/// that `write_all` retries.
+pub(crate) struct FallbackWriter<W, F> {
+    inner: W,
+    fallback: Option<F>,
+}
+
+impl<W: Write, F: Write> FallbackWriter<W, F> {
+    pub(crate) fn new(inner: W, fallback: Option<F>) -> Self {
+        Self { inner, fallback }
+    }
+}
+
+impl<W: Write, F: Write> Write for FallbackWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.inner.write(buf) {
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => Err(error),
+            Err(_) => {
+                stats::count_write_error();
+                if let Some(fallback) = &mut self.fallback {
+                    let _ = fallback.write_all(buf);
+                }
+                Ok(buf.len())
+            }
+            written => written,
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.inner.flush().is_err() {
+            stats::count_write_error();
+        }
+        if let Some(fallback) = &mut self.fallback {
+            let _ = fallback.flush();
+        }
+        Ok(())
+    }
+}
+
+/// Write times since the previous status of `set_write_latency_interval`
+pub(crate) struct LatencyState {
+    /// When the previous status was due
+    pub(crate) last_status: Instant,
+    samples: Vec<Duration>,
+    writes: u64,
+}
+
+impl LatencyState {
+    pub(crate) fn new() -> Self {
+        Self {
+            last_status: Instant::now(),
+            samples: Vec::new(),
+            writes: 0,
+        }
+    }
+
+    fn note(&mut self, elapsed: Duration) {
+        self.writes += 1;
+        if self.samples.len() < MAX_LATENCY_SAMPLES {
+            self.samples.push(elapsed);
+        }
+    }
+
+    /// The status line for the writes since the previous one, if there were
+    /// any, starting over
+    pub(crate) fn status(&mut self, interval: Duration) -> Option<String> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        self.samples.sort_unstable();
+        let percentile = |percent: usize| {
+            let rank = (self.samples.len() * percent + 99) / 100;
+            self.samples[rank.max(1) - 1]
+        };
+        let status = format!(
+            "Write latency p50 {:?}, p99 {:?}, max {:?} over {} writes in the last {}s",
+            percentile(50),
+            percentile(99),
+            self.samples[self.samples.len() - 1],
+            self.writes,
+            interval.as_secs_f64()
+        );
+        self.samples.clear();
+        self.writes = 0;
+        Some(status)
+    }
+}
+
+/// Writes to `inner`, timing each record from its first write to the flush
+/// env_logger ends it with
+pub(crate) struct TimedWriter<W> {
+    inner: W,
+    latency: Arc<Mutex<LatencyState>>,
+    /// Time spent in writes since the last flush
+    pending: Duration,
+}
+
+impl<W: Write> TimedWriter<W> {
+    pub(crate) fn new(inner: W, latency: Arc<Mutex<LatencyState>>) -> Self {
+        Self {
+            inner,
+            latency,
+            pending: Duration::ZERO,
+        }
+    }
+}
+
+impl<W: Write> Write for TimedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        let written = self.inner.write(buf);
+        self.pending += start.elapsed();
+        written
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let start = Instant::now();
+        let written = self.inner.write_all(buf);
+        self.pending += start.elapsed();
+        written
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let start = Instant::now();
+        let flushed = self.inner.flush();
+        let elapsed = std::mem::take(&mut self.pending) + start.elapsed();
+        self.latency
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .note(elapsed);
+        flushed
+    }
+}
+
+/// A standard stream the console output is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub(crate) fn write_record(self, record: &[u8]) {
+        // the console has no fallback, a failed write is only counted
+        let written = match self {
+            Stream::Stdout => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(record).and_then(|()| stdout.flush())
+            }
+            Stream::Stderr => io::stderr().lock().write_all(record),
+        };
+        if written.is_err() {
+            stats::count_write_error();
+        }
+    }
+}
+
+/// The records held while the console is paused
+struct Held {
+    capacity: usize,
+    policy: ResumePolicy,
+    records: VecDeque<(Stream, Vec<u8>)>,
+    /// Records dropped for a full buffer or by `ResumePolicy::Discard`
+    dropped: u64,
+}
+
+/// Whether the console is paused, and what it held back meanwhile
+pub(crate) struct PauseState {
+    paused: AtomicBool,
+    held: Mutex<Held>,
+}
+
+/// The pause state of every console output of the installed logger
+pub(crate) static PAUSE: PauseState = PauseState::new();
+
+impl PauseState {
+    const fn new() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            held: Mutex::new(Held {
+                capacity: 1000,
+                policy: ResumePolicy::Flush,
+                records: VecDeque::new(),
+                dropped: 0,
+            }),
+        }
+    }
+
+    fn held(&self) -> std::sync::MutexGuard<'_, Held> {
+        self.held
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Set the buffer size and resume policy, see `LoggingConfig::set_pause_capacity`
+    pub(crate) fn configure(&self, capacity: usize, policy: ResumePolicy) {
+        let mut held = self.held();
+        held.capacity = capacity;
+        held.policy = policy;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Hold `record` for `stream`, or write it if the console was resumed
+    /// in the meantime
+    fn hold(&self, stream: Stream, record: Vec<u8>, write: impl FnOnce(Stream, &[u8])) {
+        let mut held = self.held();
+        if !self.is_paused() {
+            return write(stream, &record);
+        }
+        if held.policy == ResumePolicy::Discard || held.capacity == 0 {
+            held.dropped += 1;
+            return;
+        }
+        if held.records.len() >= held.capacity {
+            held.records.pop_front();
+            held.dropped += 1;
+        }
+        held.records.push_back((stream, record));
+    }
+
+    /// Write the held records through `write` and end the pause. Returns
+    /// the notice about the records dropped, if any were.
+    pub(crate) fn resume(&self, write: impl Fn(Stream, &[u8])) -> Option<String> {
+        let mut held = self.held();
+        // records logged while these are written wait for the lock, so they
+        // follow the held ones
+        for (stream, record) in held.records.drain(..) {
+            write(stream, &record);
+        }
+        self.paused.store(false, Ordering::Relaxed);
+        let dropped = std::mem::take(&mut held.dropped);
+        (dropped > 0).then(|| match held.policy {
+            ResumePolicy::Flush => format!(
+                "{dropped} records were dropped while the console was paused, as at most {} are held",
+                held.capacity
+            ),
+            ResumePolicy::Discard => {
+                format!("{dropped} records were discarded while the console was paused")
+            }
+        })
+    }
+}
+
+/// Writes to a standard stream, holding the records back while `PAUSE` is
+/// paused. A record ends at the flush env_logger ends it with.
+pub(crate) struct PauseWriter {
+    stream: Stream,
+    state: &'static PauseState,
+    /// The bytes of a record written while paused, until its flush
+    record: Vec<u8>,
+}
+
+impl PauseWriter {
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self {
+            stream,
+            state: &PAUSE,
+            record: Vec::new(),
+        }
+    }
+}
+
+impl Write for PauseWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.record.is_empty() && !self.state.is_paused() {
+            return match self.stream {
+                Stream::Stdout => io::stdout().write(buf),
+                Stream::Stderr => io::stderr().write(buf),
+            };
+        }
+        self.record.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.record.is_empty() {
+            return match self.stream {
+                Stream::Stdout => io::stdout().flush(),
+                Stream::Stderr => io::stderr().flush(),
+            };
+        }
+        let record = std::mem::take(&mut self.record);
+        self.state.hold(self.stream, record, Stream::write_record);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A writer that fails every write, like a file on a full disk
+    struct Failing;
+
+    impl Write for Failing {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+        }
+    }
+
+    #[test]
+    fn test_pause_state() {
+        let state = PauseState::new();
+        state.configure(2, ResumePolicy::Flush);
+        let written = Mutex::new(Vec::new());
+        let write = |stream: Stream, record: &[u8]| {
+            written
+                .lock()
+                .unwrap()
+                .push((stream, String::from_utf8(record.to_vec()).unwrap()));
+        };
+
+        state.hold(Stream::Stderr, b"not paused\n".to_vec(), write);
+        state.pause();
+        for record in ["first\n", "second\n", "third\n"] {
+            state.hold(Stream::Stderr, record.as_bytes().to_vec(), write);
+        }
+        state.hold(Stream::Stdout, b"fourth\n".to_vec(), write);
+        assert_eq!(written.lock().unwrap().len(), 1);
+        assert_eq!(
+            state.resume(write).unwrap(),
+            "2 records were dropped while the console was paused, as at most 2 are held"
+        );
+        assert_eq!(
+            *written.lock().unwrap(),
+            [
+                (Stream::Stderr, String::from("not paused\n")),
+                (Stream::Stderr, String::from("third\n")),
+                (Stream::Stdout, String::from("fourth\n")),
+            ]
+        );
+        assert_eq!(state.resume(write), None);
+
+        state.configure(2, ResumePolicy::Discard);
+        state.pause();
+        state.hold(Stream::Stderr, b"discarded\n".to_vec(), write);
+        assert_eq!(
+            state.resume(write).unwrap(),
+            "1 records were discarded while the console was paused"
+        );
+        assert_eq!(written.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_fallback_writer() {
+        let before = stats::Stats::snapshot().write_errors;
+
+        let mut writer = FallbackWriter::new(Failing, Some(Vec::new()));
+        writer.write_all(b"first\n").unwrap();
+        writer.write_all(b"second\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.fallback.as_deref(), Some(&b"first\nsecond\n"[..]));
+
+        let mut writer = FallbackWriter::new(Failing, None::<Vec<u8>>);
+        writer.write_all(b"lost\n").unwrap();
+        assert!(stats::Stats::snapshot().write_errors >= before + 4);
+
+        let mut writer = FallbackWriter::new(Vec::new(), Some(Vec::new()));
+        writer.write_all(b"kept\n").unwrap();
+        assert_eq!(writer.inner, b"kept\n");
+        assert_eq!(writer.fallback.unwrap(), b"");
+    }
+
+    #[test]
+    fn test_latency_status() {
+        let latency = Arc::new(Mutex::new(LatencyState::new()));
+        let mut writer = TimedWriter::new(Vec::new(), Arc::clone(&latency));
+        writer.write_all(b"first\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.inner, b"first\n");
+        assert_eq!(latency.lock().unwrap().writes, 1);
+
+        let mut state = LatencyState::new();
+        assert_eq!(state.status(Duration::from_secs(30)), None);
+        for micros in (1..=100).rev() {
+            state.note(Duration::from_micros(micros));
+        }
+        assert_eq!(
+            state.status(Duration::from_secs(30)).unwrap(),
+            "Write latency p50 50µs, p99 99µs, max 100µs over 100 writes in the last 30s"
+        );
+        assert_eq!(state.status(Duration::from_secs(30)), None);
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{ColorPreference, LoggingConfig};
+
+/// The start and stop banners around a run, the stop banner written on drop
+/// of the guard that installed the logger
+#[test]
+fn test_banner_output() {
+    let (capture, guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_banners("=== logging started ===", "=== logging stopped ==="),
+    );
+    // the guard of an init that installed nothing leaves the running logger alone
+    drop(
+        LoggingConfig::new()
+            .set_summary_on_drop(true)
+            .set_banners("=== second start ===", "=== second stop ===")
+            .init(),
+    );
+    common::log_every_level();
+    drop(guard);
+    assert_eq!(
+        capture.masked(),
+        [
+            "=== logging started ===\n",
+            "[ERROR][0000-00-00T00:00:00]error message\n",
+            "[WARN ][0000-00-00T00:00:00]warn message\n",
+            "[INFO ][0000-00-00T00:00:00]info message\n",
+            "=== logging stopped ===\n",
+        ]
+        .concat()
+    );
+}
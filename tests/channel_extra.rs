@@ -0,0 +1,70 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{ChannelLogger, ColorPreference, LoggingConfig};
+use std::sync::{mpsc, Arc};
+
+/// A `ChannelLogger` given as the extra logger reuses the sequence numbers of
+/// the output, and its records are not counted a second time
+#[test]
+fn test_channel_extra_logger() {
+    let (sender, receiver) = mpsc::channel();
+    let channel = ChannelLogger::unbounded(
+        sender,
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_sequence_numbers(true),
+    );
+    let (capture, guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_sequence_numbers(true)
+            .set_summary_on_drop(true)
+            .set_extra_logger(Some(Arc::new(channel))),
+    );
+    log::info!("tuned");
+    log::warn!("weak signal");
+    drop(guard);
+    assert_eq!(
+        capture.masked(),
+        [
+            "[0][INFO ][0000-00-00T00:00:00]tuned\n",
+            "[0][WARN ][0000-00-00T00:00:00]weak signal\n",
+            "[0][INFO ][0000-00-00T00:00:00]Logged 0 error, 0 warn, 0 info, 0 debug, 0 trace records\n",
+        ]
+        .concat()
+    );
+
+    // without the timestamps, which the capture masks
+    let lines: Vec<String> = receiver
+        .try_iter()
+        .map(|line| {
+            let (sequence, rest) = line.line.split_at(line.line.find("][").unwrap() + 1);
+            let (level, rest) = rest.split_at(rest.find("][").unwrap() + 1);
+            format!("{sequence}{level}{}", &rest[rest.find(']').unwrap() + 1..])
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "[1][INFO ]tuned",
+            "[2][WARN ]weak signal",
+            "[3][INFO ]Logged 0 error, 1 warn, 1 info, 0 debug, 0 trace records",
+        ]
+    );
+}
@@ -0,0 +1,42 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use log::LevelFilter;
+use sdre_rust_logging::{ColorPreference, LoggingConfig};
+
+/// The default theme when color is forced
+#[test]
+fn test_color_output() {
+    let (capture, _guard) = common::install(
+        LoggingConfig::new()
+            .set_level(LevelFilter::Trace)
+            .set_color(ColorPreference::Always),
+    );
+    common::log_every_level();
+    assert_eq!(
+        capture.masked(),
+        [
+            "[\x1b[1m\x1b[31mERROR\x1b[0m][\x1b[1m\x1b[38;2;159;80;01m0000-00-00T00:00:00\x1b[0m]error message\n",
+            "[\x1b[1m\x1b[33mWARN \x1b[0m][\x1b[1m\x1b[38;2;159;80;01m0000-00-00T00:00:00\x1b[0m]warn message\n",
+            "[\x1b[1m\x1b[32mINFO \x1b[0m][\x1b[1m\x1b[38;2;159;80;01m0000-00-00T00:00:00\x1b[0m]info message\n",
+            "[\x1b[1m\x1b[36mDEBUG\x1b[0m][\x1b[1m\x1b[38;2;159;80;01m0000-00-00T00:00:00\x1b[0m]debug message\n",
+            "[\x1b[1m\x1b[35mTRACE\x1b[0m][\x1b[1m\x1b[38;2;159;80;01m0000-00-00T00:00:00\x1b[0m]trace message\n",
+        ]
+        .concat()
+    );
+}
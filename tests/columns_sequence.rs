@@ -0,0 +1,74 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use sdre_rust_logging::{LogFormat, LogTarget, LoggingConfig, Sink};
+use std::fs;
+
+/// Columns output takes no sequence numbers, so the outputs writing them
+/// have no gaps and agree on the number of each record
+#[test]
+fn test_columns_sequence() {
+    let dir = std::env::temp_dir();
+    let path = |name: &str| {
+        dir.join(format!(
+            "sdre-columns-sequence-{}.{name}",
+            std::process::id()
+        ))
+    };
+    let (columns, first, second) = (path("log"), path("first.json"), path("second.json"));
+
+    // a standalone logger shares the process wide counter
+    let standalone = LoggingConfig::new()
+        .set_format(LogFormat::Columns)
+        .set_sequence_numbers(true)
+        .set_target(LogTarget::File(columns.clone()))
+        .build();
+    standalone.log(
+        &log::Record::builder()
+            .args(format_args!("scanning"))
+            .level(log::Level::Info)
+            .target("columns_sequence")
+            .build(),
+    );
+    standalone.flush();
+
+    let guard = LoggingConfig::new()
+        .set_format(LogFormat::Columns)
+        .set_sequence_numbers(true)
+        .set_target(LogTarget::File(columns.clone()))
+        .add_sink(Sink::new(LogTarget::File(first.clone())).set_format(LogFormat::Json))
+        .add_sink(Sink::new(LogTarget::File(second.clone())).set_format(LogFormat::Json))
+        .init();
+    log::info!("tuned");
+    log::warn!("weak signal");
+    drop(guard);
+
+    let read = |path| {
+        let output = fs::read_to_string(path).unwrap();
+        let _ = fs::remove_file(path);
+        output
+    };
+    let (columns, first, second) = (read(&columns), read(&first), read(&second));
+
+    assert_eq!(columns.lines().count(), 3);
+    for output in [first, second] {
+        let sequences: Vec<&str> = output
+            .lines()
+            .map(|line| line.split(',').nth(1).unwrap())
+            .collect();
+        assert_eq!(sequences, ["\"sequence\":1", "\"sequence\":2"]);
+    }
+}
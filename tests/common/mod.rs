@@ -0,0 +1,83 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+//! Shared helpers for the integration tests. Each test file is its own
+//! process, so each can install one logger.
+
+use sdre_rust_logging::env_logger::Target;
+use sdre_rust_logging::{LoggingConfig, LoggingGuard};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// An in-memory writer the logger output is captured in
+#[derive(Clone, Default)]
+pub struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Capture {
+    /// Everything written so far, with the digits of the timestamps
+    /// replaced by `0` so the output can be compared exactly. Digits inside
+    /// ANSI escape sequences are kept.
+    pub fn masked(&self) -> String {
+        let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+        let mut escape = false;
+        output
+            .chars()
+            .map(|c| {
+                match c {
+                    '\x1b' => escape = true,
+                    'm' if escape => escape = false,
+                    c if c.is_ascii_digit() && !escape => return '0',
+                    _ => {}
+                }
+                c
+            })
+            .collect()
+    }
+}
+
+/// Install `config` as the logger, writing into the returned capture
+pub fn install(config: LoggingConfig) -> (Capture, LoggingGuard) {
+    let capture = Capture::default();
+    let target = capture.clone();
+    let guard = config
+        .set_builder_hook(move |builder| {
+            builder.target(Target::Pipe(Box::new(target.clone())));
+        })
+        .init();
+    (capture, guard)
+}
+
+/// Log one message at every level, from error to trace
+// each test file compiles this module on its own, and not all of them use it
+#[allow(dead_code)]
+pub fn log_every_level() {
+    log::error!("error message");
+    log::warn!("warn message");
+    log::info!("info message");
+    log::debug!("debug message");
+    log::trace!("trace message");
+}
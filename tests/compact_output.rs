@@ -0,0 +1,43 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use log::LevelFilter;
+use sdre_rust_logging::LoggingConfig;
+
+/// The compact preset with a header line
+#[test]
+fn test_compact_output() {
+    let (capture, _guard) = common::install(
+        LoggingConfig::compact()
+            .set_level(LevelFilter::Trace)
+            .set_header(true),
+    );
+    common::log_every_level();
+    assert_eq!(
+        capture.masked(),
+        [
+            "timestamp level message\n",
+            "00:00:00 E error message\n",
+            "00:00:00 W warn message\n",
+            "00:00:00 I info message\n",
+            "00:00:00 D debug message\n",
+            "00:00:00 T trace message\n",
+        ]
+        .concat()
+    );
+}
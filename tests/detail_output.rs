@@ -0,0 +1,46 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use sdre_rust_logging::{ColorPreference, LogTarget, LoggingConfig, Sink};
+use std::fs;
+
+/// The `detail` key-value is left out of the main output and kept in full
+/// in the sink
+#[test]
+fn test_detail_output() {
+    let dir = std::env::temp_dir();
+    let console = dir.join(format!("sdre-detail-{}.log", std::process::id()));
+    let full = dir.join(format!("sdre-detail-{}-full.log", std::process::id()));
+
+    let guard = LoggingConfig::new()
+        .set_color(ColorPreference::Never)
+        .set_target(LogTarget::File(console.clone()))
+        .set_show_detail(false)
+        .add_sink(Sink::new(LogTarget::File(full.clone())))
+        .init();
+    log::error!(icao = "a1b2c3", detail = "8d a1 b2 c3 99"; "Frame failed to decode");
+    drop(guard);
+
+    let console_output = fs::read_to_string(&console).unwrap();
+    let full_output = fs::read_to_string(&full).unwrap();
+    let _ = fs::remove_file(&console);
+    let _ = fs::remove_file(&full);
+
+    assert!(console_output.ends_with("]Frame failed to decode icao=a1b2c3\n"));
+    assert!(
+        full_output.ends_with("]Frame failed to decode icao=a1b2c3 detail=\"8d a1 b2 c3 99\"\n")
+    );
+}
@@ -0,0 +1,40 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use log::{Log, Metadata, Record};
+use sdre_rust_logging::{capture_early_logs, current_config, LoggingConfig};
+
+struct Silent;
+
+impl Log for Silent {
+    fn enabled(&self, _: &Metadata) -> bool {
+        false
+    }
+
+    fn log(&self, _: &Record) {}
+
+    fn flush(&self) {}
+}
+
+/// With another logger installed, neither the early capture nor a later
+/// `init` take over: the other logger keeps receiving the records
+#[test]
+fn test_early_capture_behind_other_logger() {
+    log::set_logger(&Silent).unwrap();
+    assert!(!capture_early_logs(16));
+    drop(LoggingConfig::new().init());
+    assert!(current_config().is_none());
+}
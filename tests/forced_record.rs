@@ -0,0 +1,58 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use log::{Level, LevelFilter};
+use sdre_rust_logging::{log_forced, ColorPreference, LoggingConfig};
+
+/// A forced record passes the level, module filter and sampling, while its
+/// neighbours at the same level are dropped
+#[test]
+fn test_forced_record() {
+    let (capture, _guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_level(LevelFilter::Warn)
+            .set_module_filter("hyper=off".parse().unwrap())
+            .set_sample_rate(Level::Warn, 1000)
+            .set_deny_messages(&["noise"]),
+    );
+    log::debug!("tuner state");
+    log_forced!(Level::Debug, "tuner state after overflow: gain {}", 40);
+    log::debug!("tuner state");
+    sdre_rust_logging::log_forced(
+        Level::Trace,
+        "hyper::client",
+        format_args!("connection reset"),
+    );
+    log::warn!("first warning is sampled");
+    log::warn!("second warning is sampled out");
+    log_forced!(Level::Warn, "forced warning");
+    log_forced!(Level::Info, "forced noise");
+
+    assert_eq!(log::max_level(), LevelFilter::Warn);
+    assert_eq!(
+        capture.masked(),
+        [
+            "[DEBUG][0000-00-00T00:00:00]tuner state after overflow: gain 00\n",
+            "[TRACE][0000-00-00T00:00:00]connection reset\n",
+            "[WARN ][0000-00-00T00:00:00]first warning is sampled\n",
+            "[WARN ][0000-00-00T00:00:00]forced warning\n",
+        ]
+        .concat()
+    );
+}
@@ -0,0 +1,45 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{ColorPreference, LoggingConfig};
+use std::thread;
+use std::time::Duration;
+
+/// A heartbeat is logged once the logger was silent for the interval, and
+/// records put it off
+#[test]
+fn test_heartbeat() {
+    let (capture, guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_heartbeat_interval(Some(Duration::from_millis(200))),
+    );
+    for _ in 0..5 {
+        log::info!("feeding");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(!capture.masked().contains("Heartbeat"));
+
+    thread::sleep(Duration::from_millis(300));
+    drop(guard);
+    let output = capture.masked();
+    assert!(output.contains("[INFO ][0000-00-00T00:00:00]Heartbeat: no records in the last 0.0s\n"));
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(capture.masked(), output);
+}
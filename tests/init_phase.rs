@@ -0,0 +1,52 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use log::LevelFilter;
+use sdre_rust_logging::{finish_init, ColorPreference, LoggingConfig};
+
+/// The init level applies until `finish_init`, then the steady level
+#[test]
+fn test_init_phase() {
+    let (capture, _guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_level(LevelFilter::Info)
+            .set_init_level(Some(LevelFilter::Debug))
+            .set_module_filter("rf=trace".parse().unwrap()),
+    );
+    log::debug!("tuning");
+    log::trace!(target: "rf", "gain steps");
+    finish_init();
+    log::debug!("frame decoded");
+    log::info!("feeding");
+    log::trace!(target: "rf", "gain changed");
+    finish_init();
+    log::debug!("frame decoded");
+
+    assert_eq!(log::max_level(), LevelFilter::Trace);
+    assert_eq!(
+        capture.masked(),
+        [
+            "[DEBUG][0000-00-00T00:00:00]tuning\n",
+            "[TRACE][0000-00-00T00:00:00]gain steps\n",
+            "[INFO ][0000-00-00T00:00:00]feeding\n",
+            "[TRACE][0000-00-00T00:00:00]gain changed\n",
+        ]
+        .concat()
+    );
+}
@@ -0,0 +1,43 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use log::LevelFilter;
+use sdre_rust_logging::{LogFormat, LoggingConfig};
+
+/// One object per line with the Vector field names
+#[test]
+fn test_json_output() {
+    let (capture, _guard) = common::install(
+        LoggingConfig::new()
+            .set_level(LevelFilter::Trace)
+            .set_format(LogFormat::Json)
+            .set_hostname(Some(String::from("receiver"))),
+    );
+    common::log_every_level();
+    assert_eq!(
+        capture.masked(),
+        [
+            "{\"timestamp\":\"0000-00-00T00:00:00.000Z\",\"host\":\"receiver\",\"level\":\"ERROR\",\"target\":\"json_output::common\",\"message\":\"error message\"}\n",
+            "{\"timestamp\":\"0000-00-00T00:00:00.000Z\",\"host\":\"receiver\",\"level\":\"WARN\",\"target\":\"json_output::common\",\"message\":\"warn message\"}\n",
+            "{\"timestamp\":\"0000-00-00T00:00:00.000Z\",\"host\":\"receiver\",\"level\":\"INFO\",\"target\":\"json_output::common\",\"message\":\"info message\"}\n",
+            "{\"timestamp\":\"0000-00-00T00:00:00.000Z\",\"host\":\"receiver\",\"level\":\"DEBUG\",\"target\":\"json_output::common\",\"message\":\"debug message\"}\n",
+            "{\"timestamp\":\"0000-00-00T00:00:00.000Z\",\"host\":\"receiver\",\"level\":\"TRACE\",\"target\":\"json_output::common\",\"message\":\"trace message\"}\n",
+        ]
+        .concat()
+    );
+}
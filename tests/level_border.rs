@@ -0,0 +1,34 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::LoggingConfig;
+
+/// `NO_COLOR` leaves out the level border along with the color, even when
+/// `CLICOLOR_FORCE` asks for color
+#[test]
+fn test_level_border_no_color() {
+    std::env::set_var("CLICOLOR_FORCE", "1");
+    std::env::set_var("NO_COLOR", "1");
+    let (capture, _guard) = common::install(LoggingConfig::new().set_level_border(Some('│')));
+    log::warn!("gain clipped");
+
+    assert_eq!(
+        capture.masked(),
+        "[WARN ][0000-00-00T00:00:00]gain clipped\n"
+    );
+}
@@ -0,0 +1,54 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{ColorPreference, ColorTheme, LoggingConfig};
+
+/// The theme and the color can be changed through the guard while logging
+#[test]
+fn test_live_theme() {
+    let (capture, guard) = common::install(LoggingConfig::new().set_color(ColorPreference::Always));
+    log::info!("default");
+    guard.set_theme(ColorTheme::named("monochrome").unwrap());
+    log::info!("monochrome");
+    guard.set_color_enabled(false);
+    log::info!("plain");
+    guard.set_color_enabled(true);
+    log::info!("colored again");
+
+    let default = ColorTheme::default();
+    let monochrome = ColorTheme::named("monochrome").unwrap();
+    assert_eq!(
+        capture.masked(),
+        [
+            format!(
+                "[{0}INFO {0:#}][{1}0000-00-00T00:00:00{1:#}]default\n",
+                default.info, default.timestamp
+            ),
+            format!(
+                "[{0}INFO {0:#}][{1}0000-00-00T00:00:00{1:#}]monochrome\n",
+                monochrome.info, monochrome.timestamp
+            ),
+            String::from("[INFO ][0000-00-00T00:00:00]plain\n"),
+            format!(
+                "[{0}INFO {0:#}][{1}0000-00-00T00:00:00{1:#}]colored again\n",
+                monochrome.info, monochrome.timestamp
+            ),
+        ]
+        .concat()
+    );
+}
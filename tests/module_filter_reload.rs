@@ -0,0 +1,48 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use log::LevelFilter;
+use sdre_rust_logging::{ColorPreference, LoggingConfig, ModuleFilter};
+
+/// A module traced for a while through the guard, then reverted, while the
+/// other targets keep the configured level
+#[test]
+fn test_module_filter_reload() {
+    let (capture, guard) = common::install(LoggingConfig::new().set_color(ColorPreference::Never));
+    assert_eq!(log::max_level(), LevelFilter::Info);
+    log::debug!(target: "receiver::rf", "before");
+
+    guard.set_module_filter("receiver::rf=trace".parse().unwrap());
+    assert_eq!(log::max_level(), LevelFilter::Trace);
+    log::trace!(target: "receiver::rf", "traced");
+    log::debug!(target: "app", "app detail");
+    log::info!(target: "app", "app info");
+
+    guard.set_module_filter(ModuleFilter::new());
+    assert_eq!(log::max_level(), LevelFilter::Info);
+    log::debug!(target: "receiver::rf", "after");
+
+    assert_eq!(
+        capture.masked(),
+        [
+            "[TRACE][0000-00-00T00:00:00]traced\n",
+            "[INFO ][0000-00-00T00:00:00]app info\n",
+        ]
+        .concat()
+    );
+}
@@ -0,0 +1,45 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{log_panics, ColorPreference, LoggingConfig};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+static PREVIOUS_RAN: AtomicBool = AtomicBool::new(false);
+
+/// A panicking thread is logged as an error, and the hook installed before
+/// still runs
+#[test]
+fn test_panic_hook() {
+    let (capture, _guard) = common::install(LoggingConfig::new().set_color(ColorPreference::Never));
+    panic::set_hook(Box::new(|_| PREVIOUS_RAN.store(true, Ordering::Relaxed)));
+    log_panics();
+
+    let result = thread::Builder::new()
+        .name(String::from("decoder"))
+        .spawn(|| panic!("empty frame from tuner {}", 1))
+        .unwrap()
+        .join();
+    assert!(result.is_err());
+    assert!(PREVIOUS_RAN.load(Ordering::Relaxed));
+
+    let output = capture.masked();
+    assert!(output.starts_with("[ERROR][0000-00-00T00:00:00]Thread 'decoder' panicked: empty frame from tuner 0 location=tests/panic_hook.rs:"));
+    assert!(output.ends_with(" thread=decoder\n"));
+}
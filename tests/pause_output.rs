@@ -0,0 +1,101 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use sdre_rust_logging::{ColorPreference, LoggingConfig, ResumePolicy};
+use std::env;
+use std::process::Command;
+
+/// Set in the child process that logs, to the resume policy it uses
+const CHILD_VAR: &str = "SDRE_PAUSE_OUTPUT_CHILD";
+
+/// Run this test again as a child process with `policy` and return the lines
+/// it wrote to stderr, without the timestamps
+fn child_stderr(policy: &str) -> Vec<String> {
+    let output = Command::new(env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "test_pause_output",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(CHILD_VAR, policy)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| match line.split_once("][") {
+            Some((level, rest)) => format!("{level}]{}", &rest[rest.find(']').unwrap() + 1..]),
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+/// Records logged while the console is paused are held until it resumes,
+/// then written or discarded per the resume policy. The console is stderr,
+/// which the test reads by running itself as a child process.
+#[test]
+fn test_pause_output() {
+    if let Some(policy) = env::var_os(CHILD_VAR) {
+        let policy = match policy.to_str() {
+            Some("discard") => ResumePolicy::Discard,
+            _ => ResumePolicy::Flush,
+        };
+        let guard = LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_pause_capacity(2)
+            .set_resume_policy(policy)
+            .init();
+        // a second init installs nothing, its settings and guard have no effect
+        let second = LoggingConfig::new()
+            .set_pause_capacity(1)
+            .set_resume_policy(ResumePolicy::Flush)
+            .init();
+        log::info!("before");
+        guard.pause();
+        for message in ["first held", "second held", "third held"] {
+            log::info!("{message}");
+        }
+        second.resume();
+        drop(second);
+        eprintln!("prompt> ");
+        guard.resume();
+        log::info!("after");
+        return;
+    }
+
+    assert_eq!(
+        child_stderr("flush"),
+        [
+            "[INFO ]before",
+            "prompt> ",
+            "[INFO ]second held",
+            "[INFO ]third held",
+            "[WARN ]1 records were dropped while the console was paused, as at most 2 are held",
+            "[INFO ]after",
+        ]
+    );
+    assert_eq!(
+        child_stderr("discard"),
+        [
+            "[INFO ]before",
+            "prompt> ",
+            "[WARN ]3 records were discarded while the console was paused",
+            "[INFO ]after",
+        ]
+    );
+}
@@ -0,0 +1,55 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{recent_logs, ColorPreference, LoggingConfig};
+
+/// The recent lines hold the last lines the output wrote, without its colors.
+/// Records are numbered and counted once, and a second init that installs
+/// nothing leaves the lines alone.
+#[test]
+fn test_recent_logs() {
+    let (capture, guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Always)
+            .set_sequence_numbers(true)
+            .set_summary_on_drop(true)
+            .set_recent_log_capacity(3),
+    );
+    common::log_every_level();
+    drop(LoggingConfig::new().set_recent_log_capacity(1).init());
+    drop(guard);
+    assert!(capture.masked().contains("\x1b["));
+
+    // without the timestamps
+    let lines: Vec<String> = recent_logs()
+        .iter()
+        .map(|line| {
+            let (prefix, rest) = line.split_at(line.find("][").unwrap() + 1);
+            let (level, rest) = rest.split_at(rest.find("][").unwrap() + 1);
+            format!("{prefix}{level}{}", &rest[rest.find(']').unwrap() + 1..])
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "[2][WARN ]warn message",
+            "[3][INFO ]info message",
+            "[4][INFO ]Logged 1 error, 1 warn, 1 info, 0 debug, 0 trace records",
+        ]
+    );
+}
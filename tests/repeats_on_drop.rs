@@ -0,0 +1,42 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use sdre_rust_logging::{ColorPreference, LoggingConfig};
+
+/// A run of repeats still going when the guard is dropped is written with its
+/// count
+#[test]
+fn test_repeats_on_drop() {
+    let (capture, guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_collapse_repeats(true),
+    );
+    log::warn!("weak signal");
+    log::warn!("weak signal");
+    log::warn!("weak signal");
+    drop(guard);
+    assert_eq!(
+        capture.masked(),
+        [
+            "[WARN ][0000-00-00T00:00:00]weak signal\n",
+            "[WARN ][0000-00-00T00:00:00]weak signal (x0)\n",
+        ]
+        .concat()
+    );
+}
@@ -0,0 +1,49 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use log::LevelFilter;
+use sdre_rust_logging::{LogFormat, LogTarget, LoggingConfig, Sink};
+use std::fs;
+
+/// Human lines in the main output and JSON in a sink, from one init
+#[test]
+fn test_sink_output() {
+    let dir = std::env::temp_dir();
+    let human = dir.join(format!("sdre-sink-{}.log", std::process::id()));
+    let json = dir.join(format!("sdre-sink-{}.json", std::process::id()));
+
+    let guard = LoggingConfig::new()
+        .set_level(LevelFilter::Info)
+        .set_target(LogTarget::File(human.clone()))
+        .add_sink(Sink::new(LogTarget::File(json.clone())).set_format(LogFormat::Json))
+        .init();
+    log::info!("tuned");
+    log::debug!("hidden");
+    drop(guard);
+
+    let human_output = fs::read_to_string(&human).unwrap();
+    let json_output = fs::read_to_string(&json).unwrap();
+    let _ = fs::remove_file(&human);
+    let _ = fs::remove_file(&json);
+
+    assert_eq!(human_output.lines().count(), 1);
+    assert!(human_output.starts_with("[INFO ]["));
+    assert!(human_output.ends_with("]tuned\n"));
+    assert_eq!(json_output.lines().count(), 1);
+    assert!(json_output.starts_with("{\"timestamp\":"));
+    assert!(json_output
+        .ends_with(",\"level\":\"INFO\",\"target\":\"sink_output\",\"message\":\"tuned\"}\n"));
+}
@@ -0,0 +1,68 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use sdre_rust_logging::{ColorPreference, LogFormat, LogTarget, LoggingConfig, Sink};
+use std::fs;
+
+/// The sinks of `add_sink` number each record as the main output does, and
+/// the records are counted once in the summary
+#[test]
+fn test_sink_sequences() {
+    let dir = std::env::temp_dir();
+    let path = |name: &str| dir.join(format!("sdre-sink-sequences-{}.{name}", std::process::id()));
+    let (main, json, human) = (path("main.log"), path("json"), path("sink.log"));
+
+    let guard = LoggingConfig::new()
+        .set_color(ColorPreference::Never)
+        .set_sequence_numbers(true)
+        .set_summary_on_drop(true)
+        .set_target(LogTarget::File(main.clone()))
+        .add_sink(Sink::new(LogTarget::File(json.clone())).set_format(LogFormat::Json))
+        .add_sink(Sink::new(LogTarget::File(human.clone())).set_color(ColorPreference::Never))
+        .init();
+    log::info!("tuned");
+    log::warn!("weak signal");
+    drop(guard);
+
+    let read = |path| {
+        let output = fs::read_to_string(path).unwrap();
+        let _ = fs::remove_file(path);
+        output
+    };
+    let (main, json, human) = (read(&main), read(&json), read(&human));
+
+    for output in [&main, &human] {
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("[1][INFO ]["));
+        assert!(lines[0].ends_with("]tuned"));
+        assert!(lines[1].starts_with("[2][WARN ]["));
+        assert!(lines[1].ends_with("]weak signal"));
+        assert!(lines[2].starts_with("[3][INFO ]["));
+        assert!(lines[2].ends_with("]Logged 0 error, 1 warn, 1 info, 0 debug, 0 trace records"));
+    }
+    let sequences: Vec<&str> = json
+        .lines()
+        .map(|line| line.split(',').nth(1).unwrap())
+        .collect();
+    assert_eq!(
+        sequences,
+        ["\"sequence\":1", "\"sequence\":2", "\"sequence\":3"]
+    );
+    assert!(
+        json.contains("\"message\":\"Logged 0 error, 1 warn, 1 info, 0 debug, 0 trace records\"")
+    );
+}
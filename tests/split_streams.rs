@@ -0,0 +1,80 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+use sdre_rust_logging::LoggingConfig;
+use std::env;
+use std::process::Command;
+
+/// Set in the child process that logs, see `test_split_streams`
+const CHILD_VAR: &str = "SDRE_SPLIT_STREAMS_CHILD";
+
+/// NDJSON on stdout and colored human lines on stderr. Both streams are
+/// written straight to the file descriptors, so the test runs itself again
+/// as a child process and reads its output.
+#[test]
+fn test_split_streams() {
+    if env::var_os(CHILD_VAR).is_some() {
+        let _guard = LoggingConfig::split_streams().init();
+        log::info!(freq = 1090; "tuned");
+        log::warn!("gain \"high\"");
+        log::debug!("hidden");
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "test_split_streams",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(CHILD_VAR, "1")
+        .env("CLICOLOR_FORCE", "1")
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // the test harness reports on stdout too, around the records and on
+    // the line of the first one
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let records: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.trim_start_matches("test test_split_streams ... "))
+        .filter(|line| line.starts_with('{'))
+        .collect();
+    assert_eq!(records.len(), 2);
+    for record in &records {
+        let object: serde_json::Value = serde_json::from_str(record).unwrap();
+        assert!(object.is_object());
+        assert!(!record.contains('\x1b'));
+    }
+    assert!(records[0].ends_with(
+        ",\"level\":\"INFO\",\"target\":\"split_streams\",\"message\":\"tuned\",\"freq\":1090}"
+    ));
+    assert!(records[1].ends_with(
+        ",\"level\":\"WARN\",\"target\":\"split_streams\",\"message\":\"gain \\\"high\\\"\"}"
+    ));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("[\x1b["));
+    assert!(lines[0].contains("INFO"));
+    assert!(lines[0].ends_with("tuned freq=1090"));
+    assert!(lines[1].contains("WARN"));
+    assert!(lines[1].ends_with("gain \"high\""));
+}
@@ -0,0 +1,48 @@
+// Copyright (C) 2023-2024 Fred Clausen
+
+// This program is free software; you can redistribute it and/or
+// modify it under the terms of the GNU General Public License
+// as published by the Free Software Foundation; either version 2
+// of the License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
+
+mod common;
+
+use chrono::{FixedOffset, Local, Offset};
+use sdre_rust_logging::{ColorPreference, LoggingConfig};
+
+/// A host whose timezone is an hour away from the expected one gets a single
+/// warning after the start banner
+#[test]
+fn test_utc_offset_warning() {
+    let local = Local::now().offset().fix();
+    let expected = FixedOffset::east_opt(local.local_minus_utc() + 3600).unwrap();
+    let (capture, _guard) = common::install(
+        LoggingConfig::new()
+            .set_color(ColorPreference::Never)
+            .set_banners("=== logging started ===", "=== logging stopped ===")
+            .set_expected_utc_offset(Some(expected)),
+    );
+    log::info!("tuned");
+    let warning = format!("Local timezone is UTC{local}, expected UTC{expected}")
+        .replace(|c: char| c.is_ascii_digit(), "0");
+    assert_eq!(
+        capture.masked(),
+        [
+            "=== logging started ===\n",
+            &format!(
+                "[WARN ][0000-00-00T00:00:00]{warning}; timestamps of this host will be off\n"
+            ),
+            "[INFO ][0000-00-00T00:00:00]tuned\n",
+        ]
+        .concat()
+    );
+}